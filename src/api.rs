@@ -0,0 +1,171 @@
+use crate::app::SessionInfo;
+
+use std::io;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tracing::debug;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ApiCommand {
+    Pause,
+    Resume,
+    Skip,
+    Stop,
+}
+
+/// Serves the local HTTP API: `GET /status`, `GET /events` and `POST /pause`, `/resume`,
+/// `/skip` and `/stop`. Commands are handed to the app, which publishes its `SessionInfo` back.
+#[derive(Clone)]
+pub struct ApiHandler {
+    pub api_to_app_sender: flume::Sender<ApiCommand>,
+    pub api_to_app_receiver: flume::Receiver<ApiCommand>,
+    pub status_sender: Arc<watch::Sender<Option<SessionInfo>>>,
+}
+
+impl ApiHandler {
+    pub fn new() -> Self {
+        let (api_to_app_sender, api_to_app_receiver) = flume::unbounded();
+        let (status_sender, _) = watch::channel(None);
+
+        ApiHandler {
+            api_to_app_sender,
+            api_to_app_receiver,
+            status_sender: Arc::new(status_sender),
+        }
+    }
+
+    pub fn publish(&self, info: SessionInfo) {
+        self.status_sender.send_replace(Some(info));
+    }
+
+    /// Serves on a listener bound up front, so a port that's taken is reported before the app
+    /// starts rather than lost in a background task.
+    pub async fn serve(self, listener: TcpListener) {
+        debug!("API: Listening on {:?}", listener.local_addr());
+
+        while let Ok((socket, peer_addr)) = listener.accept().await {
+            let handler_clone = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handler_clone.handle_connection(socket).await {
+                    debug!("API: Connection with {:?} failed: {:?}", peer_addr, e);
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(&self, mut socket: TcpStream) -> io::Result<()> {
+        let mut buf = [0; 4096];
+        let n = socket.read(&mut buf).await?;
+        let request = String::from_utf8_lossy(&buf[..n]);
+
+        let mut request_line = request.split_whitespace();
+        let method = request_line.next().unwrap_or_default();
+        let path = request_line
+            .next()
+            .and_then(|path| path.split('?').next())
+            .unwrap_or_default();
+
+        let command = match path {
+            "/pause" => Some(ApiCommand::Pause),
+            "/resume" => Some(ApiCommand::Resume),
+            "/skip" => Some(ApiCommand::Skip),
+            "/stop" => Some(ApiCommand::Stop),
+            _ => None,
+        };
+
+        match (method, path, command) {
+            ("GET", "/status", _) => {
+                let status = self.status_sender.borrow().clone();
+                match status {
+                    Some(info) => {
+                        let body = serde_json::to_string(&info)?;
+                        write_response(&mut socket, "200 OK", &body).await
+                    }
+                    None => {
+                        let body = r#"{"error":"no session"}"#;
+                        write_response(&mut socket, "503 Service Unavailable", body).await
+                    }
+                }
+            }
+            ("GET", "/events", _) => self.stream_events(socket).await,
+            ("POST", _, Some(command)) => {
+                self.api_to_app_sender
+                    .send_async(command)
+                    .await
+                    .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+                write_response(&mut socket, "202 Accepted", "").await
+            }
+            (_, "/status" | "/events", _) | (_, _, Some(_)) => {
+                write_response(&mut socket, "405 Method Not Allowed", "").await
+            }
+            _ => write_response(&mut socket, "404 Not Found", "").await,
+        }
+    }
+
+    /// Streams a `phase` event whenever the timer changes, and a `tick` event every second.
+    async fn stream_events(&self, mut socket: TcpStream) -> io::Result<()> {
+        socket
+            .write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n",
+            )
+            .await?;
+
+        let mut status_receiver = self.status_sender.subscribe();
+        let mut last: Option<SessionInfo> = None;
+
+        loop {
+            let info = status_receiver.borrow_and_update().clone();
+
+            if let Some(info) = info {
+                let event = match &last {
+                    Some(last) if !is_new_phase(last, &info) => (last.timer.remaining
+                        != info.timer.remaining
+                        || last.timer.paused != info.timer.paused)
+                        .then_some("tick"),
+                    _ => Some("phase"),
+                };
+
+                if let Some(event) = event {
+                    let data = serde_json::to_string(&info)?;
+                    socket
+                        .write_all(format!("event: {}\ndata: {}\n\n", event, data).as_bytes())
+                        .await?;
+                }
+                last = Some(info);
+            }
+
+            if status_receiver.changed().await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl Default for ApiHandler {
+    fn default() -> Self {
+        ApiHandler::new()
+    }
+}
+
+fn is_new_phase(last: &SessionInfo, info: &SessionInfo) -> bool {
+    last.timer.name != info.timer.name
+        || last.pomodoro.as_ref().map(|pomodoro| pomodoro.state)
+            != info.pomodoro.as_ref().map(|pomodoro| pomodoro.state)
+        || last.mob.as_ref().map(|mob| mob.rotation) != info.mob.as_ref().map(|mob| mob.rotation)
+        || last.standup.as_ref().map(|standup| standup.current_speaker)
+            != info.standup.as_ref().map(|standup| standup.current_speaker)
+}
+
+async fn write_response(socket: &mut TcpStream, status: &str, body: &str) -> io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+
+    socket.write_all(response.as_bytes()).await?;
+    socket.shutdown().await
+}
@@ -1,32 +1,171 @@
+use crate::api::{ApiCommand, ApiHandler};
+use crate::checkpoint::{Checkpoint, Checkpointer};
+use crate::clock;
 use crate::event::{Event, Events};
+use crate::flowtime::{BreakRule, FlowtimeSession};
+use crate::metrics::METRICS;
+use crate::mob::{MobSession, MobState};
+use crate::parser::{parse_named_duration, parse_time_of_day, ParseTimeError};
+use crate::plan::{Plan, PlanSession, Step};
 use crate::pomodoro::{Pomodoro, PomodoroSession, PomodoroState};
-use crate::timer::{Timer, TimerAction, TimerSession, TimerStatus};
+use crate::session::{Session, SessionSnapshot, SessionStatus};
+use crate::standup::{Speaker, StandupSession};
+use crate::stats::StatsRecorder;
+use crate::stopwatch::{Lap, StopwatchSession};
+use crate::timer::{SuspendPolicy, Timer, TimerAction, TimerSession};
 use crate::tui;
 use crate::ui;
-use crate::websocket::{TimerMessage, WebSocketHandler};
+use crate::vote::{Ballot, Proposal, VoteRule, EXTEND_FOCUS_DURATION};
+use crate::webhook::WebhookNotifier;
+use crate::websocket::{Role, TimerMessage, WebSocketHandler};
 
-use crossterm::event::{Event as CrosstermEvent, KeyCode, KeyModifiers};
-use std::io;
+use chrono::Local;
+use crossterm::event::{Event as CrosstermEvent, KeyCode, KeyEvent, KeyModifiers};
+use serde::{Serialize, Serializer};
+use std::io::{self, Write};
 use std::net::SocketAddr;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tracing::debug;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct SessionInfo {
     pub timer: TimerInfo,
     pub pomodoro: Option<PomodoroInfo>,
+    pub mob: Option<MobInfo>,
+    pub standup: Option<StandupInfo>,
+    pub stopwatch: Option<StopwatchInfo>,
+    pub plan: Option<PlanInfo>,
+    pub flowtime: Option<FlowtimeInfo>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct TimerInfo {
     pub name: String,
+    #[serde(serialize_with = "serialize_secs")]
     pub remaining: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    pub duration: Duration,
+    pub paused: bool,
+    /// Wall-clock finish time in seconds since the Unix epoch, while running
+    pub deadline: Option<u64>,
+    /// Time past the deadline while in overtime
+    #[serde(serialize_with = "serialize_secs")]
+    pub overrun: Duration,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct PomodoroInfo {
+    pub state: PomodoroState,
+    /// The daily target, 0 when cycling until stopped
     pub total_sessions: usize,
     pub current_session: usize,
+    pub long_break_every: usize,
+    pub set_position: usize,
+    pub completed_today: usize,
+    /// The phase waiting for a key press while `state` is `Ready`
+    pub pending: Option<PomodoroState>,
+    #[serde(serialize_with = "serialize_secs")]
+    pub waited: Duration,
+    /// Time phases ran past their end in overtime
+    #[serde(serialize_with = "serialize_secs")]
+    pub overrun: Duration,
+    /// The phase after this one, unless the day ends with it
+    pub next_phase: Option<PomodoroState>,
+    #[serde(serialize_with = "serialize_secs")]
+    pub next_duration: Duration,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MobInfo {
+    pub driver: Option<String>,
+    pub next_driver: Option<String>,
+    pub rotation: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StandupInfo {
+    pub current_speaker: usize,
+    pub total_speakers: usize,
+    pub next_speaker: Option<String>,
+    #[serde(serialize_with = "serialize_secs")]
+    pub overrun: Duration,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StopwatchInfo {
+    #[serde(serialize_with = "serialize_secs")]
+    pub elapsed: Duration,
+    pub laps: Vec<Lap>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanInfo {
+    pub title: Option<String>,
+    /// 1-based, like the sessions of a pomodoro
+    pub current_step: usize,
+    pub total_steps: usize,
+    /// The step ran out and waits for a key press to move on
+    pub waiting: bool,
+    pub upcoming: Vec<Step>,
+    /// Left of the current step and all the upcoming ones
+    #[serde(serialize_with = "serialize_secs")]
+    pub remaining: Duration,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FlowtimeInfo {
+    /// Time spent in the focus sessions ended so far
+    #[serde(serialize_with = "serialize_secs")]
+    pub focused: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    pub last_focus: Duration,
+    /// How long the running focus has gone on
+    #[serde(serialize_with = "serialize_secs")]
+    pub running_focus: Duration,
+    /// The break ending the running focus would earn
+    #[serde(serialize_with = "serialize_secs")]
+    pub earned_break: Duration,
+}
+
+fn serialize_secs<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_secs())
+}
+
+impl From<&SessionInfo> for SessionStatus {
+    fn from(info: &SessionInfo) -> Self {
+        SessionStatus {
+            remaining_secs: info.timer.remaining.as_secs(),
+            duration_secs: info.timer.duration.as_secs(),
+            paused: info.timer.paused,
+            phase: info.pomodoro.as_ref().map(|pomodoro| pomodoro.state),
+            current_session: info
+                .pomodoro
+                .as_ref()
+                .map_or(0, |pomodoro| pomodoro.current_session),
+            total_sessions: info
+                .pomodoro
+                .as_ref()
+                .map_or(0, |pomodoro| pomodoro.total_sessions),
+        }
+    }
+}
+
+/// One row of the session list shown once more than one session is running.
+#[derive(Debug, Clone)]
+pub struct SessionEntry {
+    pub name: String,
+    pub label: String,
+    pub ratio: f64,
+    pub paused: bool,
+    pub overtime: bool,
+    pub selected: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct VoteInfo {
+    pub proposal: Proposal,
+    pub yes: usize,
+    pub no: usize,
 }
 
 enum SessionType {
@@ -34,107 +173,301 @@ enum SessionType {
     Shared(WebSocketHandler),
 }
 
-pub trait Session: Send {
-    fn tick(&mut self);
-    fn is_finished(&self) -> bool;
-    fn toggle_pause(&mut self);
-    fn get_timer(&mut self) -> Option<&mut Timer>;
-    fn get_pomodoro(&mut self) -> Option<&mut Pomodoro>;
-}
-
 pub struct App {
     session: Box<dyn Session>,
     tick_rate: Duration,
     mode: Mode,
     session_type: SessionType,
+    vote_rule: VoteRule,
+    ballot: Option<Ballot>,
+    stats: Option<StatsRecorder>,
+    last_status: Option<SessionStatus>,
+    /// Focus sessions the main session had completed when last published, so the metrics count
+    /// only the ones finished since, not those carried over from a checkpoint
+    completed: Option<usize>,
+    api: Option<ApiHandler>,
+    webhooks: Option<WebhookNotifier>,
+    suspend_policy: SuspendPolicy,
+    // Time away the user still has to decide about under `SuspendPolicy::Ask`
+    suspend_gap: Option<Duration>,
+    checkpointer: Option<Checkpointer>,
+    /// Sessions added from inside the TUI next to the main one, such as reminders. The API,
+    /// webhooks, checkpoints and peers only ever follow the main session.
+    extras: Vec<Box<dyn Session>>,
+    /// 0 is the main session, the extra ones follow from 1
+    selected: usize,
+    /// What has been typed for a new timer while its prompt is open
+    prompt: Option<String>,
+    prompt_error: Option<String>,
+    notice: Option<(String, Instant)>,
+    /// Whether to ring the terminal bell with the next frame
+    bell: bool,
+    main_finished: bool,
 }
 
+/// How long a notice that a session finished stays on screen
+const NOTICE_DURATION: Duration = Duration::from_secs(10);
+
 pub enum Mode {
     Timer,
     Pomodoro,
+    Mob,
+    Standup,
+    Stopwatch,
+    Plan,
+    Flowtime,
+}
+
+impl From<&SessionSnapshot> for Mode {
+    fn from(snapshot: &SessionSnapshot) -> Self {
+        match snapshot {
+            SessionSnapshot::Timer(_) => Mode::Timer,
+            SessionSnapshot::Pomodoro(_) => Mode::Pomodoro,
+            SessionSnapshot::Mob(_) => Mode::Mob,
+            SessionSnapshot::Standup(_) => Mode::Standup,
+            SessionSnapshot::Stopwatch(_) => Mode::Stopwatch,
+            SessionSnapshot::Plan(_) => Mode::Plan,
+            SessionSnapshot::Flowtime(_) => Mode::Flowtime,
+        }
+    }
 }
 
 impl App {
     pub fn new_timer(duration: Duration, name: String, tick_rate: Duration) -> Self {
-        App {
-            session: Box::new(TimerSession::new(duration, name)),
+        Self::from_session(
+            Box::new(TimerSession::new(duration, name)),
+            Mode::Timer,
             tick_rate,
-            mode: Mode::Timer,
-            session_type: SessionType::SingleUser,
+            SessionType::SingleUser,
+        )
+    }
+
+    pub fn new_pomodoro(pomodoro: Pomodoro, tick_rate: Duration) -> Self {
+        Self::from_session(
+            Box::new(PomodoroSession::new(pomodoro)),
+            Mode::Pomodoro,
+            tick_rate,
+            SessionType::SingleUser,
+        )
+    }
+
+    pub fn new_flowtime(rule: BreakRule, total_sessions: usize, tick_rate: Duration) -> Self {
+        Self::from_session(
+            Box::new(FlowtimeSession::new(rule, total_sessions)),
+            Mode::Flowtime,
+            tick_rate,
+            SessionType::SingleUser,
+        )
+    }
+
+    /// Picks a checkpointed session back up, or `None` if it ran out while nothing was running.
+    pub fn resume(checkpoint: Checkpoint, tick_rate: Duration) -> Option<Self> {
+        let snapshot = checkpoint.snapshot_by_now();
+        let mode = Mode::from(&snapshot);
+        let mut session = snapshot.into_session();
+
+        if session
+            .get_timer()
+            .is_none_or(|timer| timer.is_done() && !timer.is_overtime())
+        {
+            return None;
         }
+
+        Some(Self::from_session(
+            session,
+            mode,
+            tick_rate,
+            SessionType::SingleUser,
+        ))
+    }
+
+    pub fn new_stopwatch(tick_rate: Duration) -> Self {
+        Self::from_session(
+            Box::new(StopwatchSession::new()),
+            Mode::Stopwatch,
+            tick_rate,
+            SessionType::SingleUser,
+        )
+    }
+
+    pub fn new_plan(plan: Plan, tick_rate: Duration) -> Self {
+        Self::from_session(
+            Box::new(PlanSession::new(plan)),
+            Mode::Plan,
+            tick_rate,
+            SessionType::SingleUser,
+        )
     }
 
-    pub fn new_pomodoro(
-        total_sessions: usize,
-        focus_duration: Duration,
+    pub fn new_mob(
+        drivers: Vec<String>,
+        turn_duration: Duration,
         break_duration: Duration,
-        long_break_duration: Duration,
+        breaks_every: usize,
         tick_rate: Duration,
     ) -> Self {
-        App {
-            session: Box::new(PomodoroSession::new(
-                total_sessions,
-                focus_duration,
+        Self::from_session(
+            Box::new(MobSession::new(
+                drivers,
+                turn_duration,
                 break_duration,
-                long_break_duration,
+                breaks_every,
             )),
+            Mode::Mob,
             tick_rate,
-            mode: Mode::Pomodoro,
-            session_type: SessionType::SingleUser,
-        }
+            SessionType::SingleUser,
+        )
+    }
+
+    pub fn new_standup(
+        speakers: Vec<Speaker>,
+        default_timebox: Duration,
+        tick_rate: Duration,
+    ) -> Self {
+        Self::from_session(
+            Box::new(StandupSession::new(speakers, default_timebox)),
+            Mode::Standup,
+            tick_rate,
+            SessionType::SingleUser,
+        )
     }
 
     pub fn new_shared_pomodoro(
-        total_sessions: usize,
-        focus_duration: Duration,
+        pomodoro: Pomodoro,
+        tick_rate: Duration,
+        role: Role,
+        vote_rule: VoteRule,
+    ) -> (Self, WebSocketHandler) {
+        let session = Box::new(PomodoroSession::new(pomodoro));
+
+        Self::new_shared(session, Mode::Pomodoro, tick_rate, role, vote_rule)
+    }
+
+    pub fn new_shared_mob(
+        drivers: Vec<String>,
+        turn_duration: Duration,
         break_duration: Duration,
-        long_break_duration: Duration,
+        breaks_every: usize,
+        tick_rate: Duration,
+        role: Role,
+        vote_rule: VoteRule,
+    ) -> (Self, WebSocketHandler) {
+        let session = Box::new(MobSession::new(
+            drivers,
+            turn_duration,
+            break_duration,
+            breaks_every,
+        ));
+
+        Self::new_shared(session, Mode::Mob, tick_rate, role, vote_rule)
+    }
+
+    pub fn new_shared_standup(
+        speakers: Vec<Speaker>,
+        default_timebox: Duration,
+        tick_rate: Duration,
+        role: Role,
+        vote_rule: VoteRule,
+    ) -> (Self, WebSocketHandler) {
+        let session = Box::new(StandupSession::new(speakers, default_timebox));
+
+        Self::new_shared(session, Mode::Standup, tick_rate, role, vote_rule)
+    }
+
+    fn new_shared(
+        session: Box<dyn Session>,
+        mode: Mode,
         tick_rate: Duration,
+        role: Role,
+        vote_rule: VoteRule,
     ) -> (Self, WebSocketHandler) {
-        let ws_handler = WebSocketHandler::new();
+        let ws_handler = WebSocketHandler::new(role);
 
         let ws_handler_clone = ws_handler.clone();
 
-        (
-            App {
-                session: Box::new(PomodoroSession::new(
-                    total_sessions,
-                    focus_duration,
-                    break_duration,
-                    long_break_duration,
-                )),
-                tick_rate,
-                mode: Mode::Pomodoro,
-                session_type: SessionType::Shared(ws_handler_clone),
-            },
-            ws_handler,
-        )
+        let mut app = Self::from_session(
+            session,
+            mode,
+            tick_rate,
+            SessionType::Shared(ws_handler_clone),
+        );
+        app.vote_rule = vote_rule;
+
+        (app, ws_handler)
+    }
+
+    fn from_session(
+        session: Box<dyn Session>,
+        mode: Mode,
+        tick_rate: Duration,
+        session_type: SessionType,
+    ) -> Self {
+        App {
+            session,
+            tick_rate,
+            mode,
+            session_type,
+            vote_rule: VoteRule::default(),
+            ballot: None,
+            stats: None,
+            last_status: None,
+            completed: None,
+            api: None,
+            webhooks: None,
+            suspend_policy: SuspendPolicy::default(),
+            suspend_gap: None,
+            checkpointer: None,
+            extras: Vec::new(),
+            selected: 0,
+            prompt: None,
+            prompt_error: None,
+            notice: None,
+            bell: false,
+            main_finished: false,
+        }
     }
 
     pub async fn run(&mut self, terminal: &mut tui::Tui) -> io::Result<()> {
-        let mut events = Events::new();
+        let mut events = Events::new(self.tick_rate);
 
         loop {
             match &self.session_type {
                 SessionType::SingleUser => {
-                    if let Some(event) = events.next().await {
-                        match event {
-                            Event::Tick => {
-                                self.session.tick();
-                            }
-                            Event::Render => {
-                                terminal.draw(|f| ui::render(f, self))?;
-                            }
-                            Event::Crossterm(CrosstermEvent::Key(key)) => {
-                                if let Some(action) = self.key_to_action(key.code, key.modifiers) {
-                                    self.handle_action(action);
+                    let api_receiver = self.api_receiver();
+
+                    tokio::select! {
+                        Some(event) = events.next() => {
+                            match event {
+                                Event::Tick => {
+                                    self.check_suspend();
+                                    self.tick_sessions();
+                                    self.save_checkpoint();
                                 }
+                                Event::Render => {
+                                    self.render(terminal)?;
+                                }
+                                Event::Crossterm(CrosstermEvent::Key(key)) => {
+                                    if self.answer_suspend(key.code) {
+                                        // The key answered the suspend question
+                                    } else if self.handle_local_key(key) {
+                                        // The key was about the session list
+                                    } else if let Some(action) = self.key_to_action(key.code, key.modifiers) {
+                                        self.handle_action(action, SocketAddr::from(([0, 0, 0, 0], 0)));
+                                    }
+                                }
+                                _ => (),
+                            }
+                        }
+                        Ok(command) = recv_api_command(api_receiver) => {
+                            if let Some(action) = self.api_command_to_action(command) {
+                                self.handle_action(action, SocketAddr::from(([0, 0, 0, 0], 0)));
                             }
-                            _ => (),
                         }
                     }
                 }
                 SessionType::Shared(ws_handler) => {
+                    let ws_handler = ws_handler.clone();
+                    let api_receiver = self.api_receiver();
                     let local_addr = *ws_handler.local_addr.lock().await;
                     let local_addr =
                         local_addr.unwrap_or_else(|| SocketAddr::from(([0, 0, 0, 0], 0)));
@@ -143,17 +476,19 @@ impl App {
                         Some(event) = events.next() => {
                             match event {
                                 Event::Tick => {
-                                    self.session.tick();
+                                    self.check_suspend();
+                                    self.tick_sessions();
+                                    self.record_stats(&ws_handler).await;
+                                    self.sync_status(&ws_handler, local_addr).await;
                                 }
                                 Event::Render => {
-                                    terminal.draw(|f| ui::render(f, self))?;
+                                    self.render(terminal)?;
                                 }
                                 Event::Crossterm(CrosstermEvent::Key(key)) => {
-                                    if let Some(action) = self.key_to_action(key.code, key.modifiers) {
-                                        let timer_message = TimerMessage { action, sender: local_addr };
-                                        ws_handler.app_to_ws_sender.send_async(timer_message.clone()).await.unwrap();
-                                        debug!("{:?} - APP(APP_TO_WS): Action({:?}) SENT TO WS", timer_message.sender, action);
-                                        self.handle_action(timer_message.action);
+                                    if self.handle_local_key(key) {
+                                        // Extra sessions are local, peers don't hear about them
+                                    } else if let Some(action) = self.key_to_action(key.code, key.modifiers) {
+                                        self.share_action(action, &ws_handler, local_addr).await;
                                     }
                                 }
                                 _ => ()
@@ -161,29 +496,417 @@ impl App {
                         }
                         Ok(timer_message) = ws_handler.ws_to_app_receiver.recv_async() => {
                             debug!("{:?} - APP(WS_TO_APP): Message RECEIVED FROM WS: {:?}", local_addr, timer_message);
-                            self.handle_ws_message(timer_message.action);
+                            self.handle_ws_message(timer_message);
+                            self.settle_ballot(&ws_handler, local_addr).await;
+                        }
+                        Ok(command) = recv_api_command(api_receiver) => {
+                            if let Some(action) = self.api_command_to_action(command) {
+                                self.share_action(action, &ws_handler, local_addr).await;
+                            }
                         }
                     }
                 }
             }
 
+            self.publish_status();
+
             if self.should_quit() {
                 break;
             }
         }
 
+        if let SessionType::Shared(ws_handler) = &self.session_type {
+            // Close the focus phase that was interrupted by quitting
+            let ws_handler = ws_handler.clone();
+            self.record_stats(&ws_handler).await;
+        }
+
+        if let Some(checkpointer) = &self.checkpointer {
+            checkpointer.clear();
+        }
+
+        if let Some(webhooks) = self.webhooks.take() {
+            webhooks.finish().await;
+        }
+
         Ok(())
     }
 
-    fn handle_ws_message(&mut self, message: TimerAction) {
-        self.handle_action(message);
+    async fn share_action(
+        &mut self,
+        action: TimerAction,
+        ws_handler: &WebSocketHandler,
+        local_addr: SocketAddr,
+    ) {
+        let timer_message = TimerMessage {
+            action,
+            sender: local_addr,
+        };
+        // A guest quitting only leaves, the session goes on for everyone else
+        if ws_handler.role == Role::Host || !matches!(action, TimerAction::Quit) {
+            ws_handler
+                .app_to_ws_sender
+                .send_async(timer_message.clone())
+                .await
+                .unwrap();
+            debug!(
+                "{:?} - APP(APP_TO_WS): Action({:?}) SENT TO WS",
+                timer_message.sender, action
+            );
+        }
+        self.handle_action(timer_message.action, timer_message.sender);
+        self.settle_ballot(ws_handler, local_addr).await;
+    }
+
+    pub fn set_api_handler(&mut self, api: ApiHandler) {
+        self.api = Some(api);
+    }
+
+    pub fn enable_checkpoints(&mut self, task: Option<String>) {
+        self.checkpointer = Some(Checkpointer::new(task));
+    }
+
+    fn save_checkpoint(&mut self) {
+        if self.checkpointer.is_none() || self.should_quit() {
+            return;
+        }
+
+        if let Some(checkpoint) = self.get_checkpoint() {
+            if let Some(checkpointer) = &mut self.checkpointer {
+                checkpointer.save_if_due(checkpoint);
+            }
+        }
+    }
+
+    fn get_checkpoint(&self) -> Option<Checkpoint> {
+        match self.mode {
+            Mode::Timer | Mode::Pomodoro | Mode::Flowtime => {
+                Some(Checkpoint::new(self.session.snapshot()))
+            }
+            Mode::Mob | Mode::Standup | Mode::Stopwatch | Mode::Plan => None,
+        }
+    }
+
+    pub fn set_suspend_policy(&mut self, policy: SuspendPolicy) {
+        self.suspend_policy = policy;
+    }
+
+    /// Applies the suspend policy when the wall clock shows the machine slept through a tick.
+    /// A shared room keeps running while one peer sleeps, so shared sessions always count the gap.
+    fn check_suspend(&mut self) {
+        let policy = match self.session_type {
+            SessionType::SingleUser => self.suspend_policy,
+            SessionType::Shared(_) => SuspendPolicy::Count,
+        };
+
+        // Reminders should still go off on waking, so extra sessions always count the gap
+        for session in &mut self.extras {
+            if let Some(timer) = session.get_timer() {
+                if let Some(gap) = timer.suspend_gap() {
+                    timer.count_gap(gap);
+                }
+            }
+        }
+
+        let Some(timer) = self.session.get_timer() else {
+            return;
+        };
+
+        // Resuming some other way settles a pending question without counting the gap
+        if self.suspend_gap.is_some() && !timer.is_paused() {
+            self.suspend_gap = None;
+        }
+
+        let Some(gap) = timer.suspend_gap() else {
+            return;
+        };
+        debug!("APP: Woke up from a {:?} suspend", gap);
+
+        match policy {
+            SuspendPolicy::Count => timer.count_gap(gap),
+            SuspendPolicy::Pause => {
+                timer.toggle_pause();
+                timer.resync_wall_clock();
+            }
+            SuspendPolicy::Ask => {
+                timer.toggle_pause();
+                timer.resync_wall_clock();
+                self.suspend_gap = Some(gap);
+            }
+        }
+    }
+
+    /// Handles the answer to "count the time away?", returning whether the key was consumed.
+    fn answer_suspend(&mut self, key: KeyCode) -> bool {
+        let Some(gap) = self.suspend_gap else {
+            return false;
+        };
+
+        let count = match key {
+            KeyCode::Char('y') => true,
+            KeyCode::Char('n') => false,
+            _ => return false,
+        };
+
+        self.suspend_gap = None;
+        if let Some(timer) = self.session.get_timer() {
+            timer.toggle_pause();
+            if count {
+                timer.count_gap(gap);
+            }
+        }
+        true
+    }
+
+    pub fn get_suspend_gap(&self) -> Option<Duration> {
+        self.suspend_gap
+    }
+
+    pub fn set_webhooks(&mut self, webhooks: WebhookNotifier) {
+        self.webhooks = Some(webhooks);
+    }
+
+    fn api_receiver(&self) -> Option<flume::Receiver<ApiCommand>> {
+        self.api.as_ref().map(|api| api.api_to_app_receiver.clone())
+    }
+
+    fn publish_status(&mut self) {
+        let info = self.get_session_info();
+        let completed = info
+            .pomodoro
+            .as_ref()
+            .map(|pomodoro| pomodoro.completed_today);
+        if let (Some(before), Some(after)) = (self.completed, completed) {
+            METRICS.pomodoros_completed(after.saturating_sub(before) as u64);
+        }
+        self.completed = completed;
+        METRICS.observe(&SessionStatus::from(&info));
+        if let Some(webhooks) = &mut self.webhooks {
+            webhooks.observe(&info);
+        }
+        if let Some(api) = &self.api {
+            api.publish(info);
+        }
+    }
+
+    fn api_command_to_action(&mut self, command: ApiCommand) -> Option<TimerAction> {
+        let paused = self.get_timer()?.is_paused();
+
+        match command {
+            ApiCommand::Pause if !paused => Some(TimerAction::Pause),
+            ApiCommand::Resume if paused => Some(TimerAction::Pause),
+            ApiCommand::Pause | ApiCommand::Resume => None,
+            ApiCommand::Skip => Some(TimerAction::Skip),
+            ApiCommand::Stop => Some(TimerAction::Quit),
+        }
+    }
+
+    /// The host pushes its view of the session whenever the displayed second changes, so browser
+    /// clients that keep no timer of their own can follow along.
+    async fn sync_status(&mut self, ws_handler: &WebSocketHandler, local_addr: SocketAddr) {
+        if ws_handler.role != Role::Host || ws_handler.peer_map.lock().await.is_empty() {
+            return;
+        }
+
+        let Some(status) = self.get_session_status() else {
+            return;
+        };
+        if self.last_status == Some(status) {
+            return;
+        }
+        self.last_status = Some(status);
+
+        let timer_message = TimerMessage {
+            action: TimerAction::Sync(status),
+            sender: local_addr,
+        };
+        ws_handler
+            .app_to_ws_sender
+            .send_async(timer_message)
+            .await
+            .unwrap();
+    }
+
+    pub fn set_stats_recorder(&mut self, recorder: StatsRecorder) {
+        self.stats = Some(recorder);
+    }
+
+    async fn record_stats(&mut self, ws_handler: &WebSocketHandler) {
+        if ws_handler.role != Role::Host {
+            return;
+        }
+
+        if let (Some(recorder), Some(pomodoro)) = (&mut self.stats, self.session.get_pomodoro()) {
+            recorder.observe(pomodoro, ws_handler.participant_names().await);
+        }
+    }
+
+    fn handle_ws_message(&mut self, message: TimerMessage) {
+        self.handle_action(message.action, message.sender);
+    }
+
+    /// Only the host decides the outcome of a ballot, everyone else applies the result it broadcasts.
+    async fn settle_ballot(&mut self, ws_handler: &WebSocketHandler, local_addr: SocketAddr) {
+        if ws_handler.role != Role::Host {
+            return;
+        }
+
+        let participants = ws_handler.participants().await;
+        let outcome = self
+            .ballot
+            .as_ref()
+            .and_then(|ballot| ballot.tally(self.vote_rule, participants, local_addr));
+
+        if let Some(passed) = outcome {
+            let timer_message = TimerMessage {
+                action: TimerAction::Resolve(passed),
+                sender: local_addr,
+            };
+            ws_handler
+                .app_to_ws_sender
+                .send_async(timer_message.clone())
+                .await
+                .unwrap();
+            debug!(
+                "{:?} - APP(APP_TO_WS): Ballot resolved, passed: {}",
+                local_addr, passed
+            );
+            self.handle_action(timer_message.action, timer_message.sender);
+        }
     }
 
     fn should_quit(&self) -> bool {
         // NOTE: timer: this returns true if the TimerStatus is Exit
         // pomodoro: this returns true if the PomodoroState is Completed
         // Exit and Completed are different things so they must be handled separately
-        self.session.is_finished()
+        self.session.is_finished() && self.extras.is_empty()
+    }
+
+    fn tick_sessions(&mut self) {
+        self.session.tick();
+        if self.session.is_finished() && !self.main_finished {
+            self.main_finished = true;
+            if !self.extras.is_empty() {
+                let name = session_name(self.session.as_mut());
+                self.notify(format!("{} is done", name));
+            }
+        }
+
+        let mut index = 0;
+        while index < self.extras.len() {
+            self.extras[index].tick();
+            if self.extras[index].is_finished() {
+                let mut session = self.extras.remove(index);
+                self.notify(format!("{} is done", session_name(session.as_mut())));
+                self.deselect(index + 1);
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    /// Rings the terminal bell and shows `message` for a while.
+    fn notify(&mut self, message: String) {
+        debug!("APP: {}", message);
+        self.bell = true;
+        self.notice = Some((message, Instant::now()));
+    }
+
+    /// Draws a frame, ringing the bell through the same backend so it can't land mid-frame.
+    fn render(&mut self, terminal: &mut tui::Tui) -> io::Result<()> {
+        terminal.draw(|f| ui::render(f, self))?;
+        if std::mem::take(&mut self.bell) {
+            let backend = terminal.backend_mut();
+            backend.write_all(b"\x07")?;
+            backend.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Keeps the selection on the same session after the one at `removed` went away.
+    fn deselect(&mut self, removed: usize) {
+        if self.selected > removed || self.selected > self.extras.len() {
+            self.selected -= 1;
+        }
+    }
+
+    /// Handles the keys for adding, removing and moving between sessions, and any key while an
+    /// extra session is selected that only concerns it. Returns whether the key was consumed.
+    fn handle_local_key(&mut self, key: KeyEvent) -> bool {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        if let Some(prompt) = &mut self.prompt {
+            match key.code {
+                // Ctrl+C still quits while typing
+                KeyCode::Char('c') | KeyCode::Char('C') if control => return false,
+                KeyCode::Char(_) if control || key.modifiers.contains(KeyModifiers::ALT) => {}
+                KeyCode::Char(c) => prompt.push(c),
+                KeyCode::Backspace => {
+                    prompt.pop();
+                }
+                KeyCode::Enter => self.submit_prompt(),
+                KeyCode::Esc => {
+                    self.prompt = None;
+                    self.prompt_error = None;
+                }
+                _ => {}
+            }
+            return true;
+        }
+
+        if control {
+            return false;
+        }
+
+        let count = self.extras.len() + 1;
+        match key.code {
+            KeyCode::Char('a') => self.prompt = Some(String::new()),
+            KeyCode::Tab | KeyCode::Down => self.selected = (self.selected + 1) % count,
+            KeyCode::BackTab | KeyCode::Up => self.selected = (self.selected + count - 1) % count,
+            KeyCode::Char('x') if self.selected > 0 => {
+                self.extras.remove(self.selected - 1);
+                self.deselect(self.selected);
+            }
+            _ if self.selected > 0 => {
+                return match self.key_to_action(key.code, key.modifiers) {
+                    Some(
+                        action @ (TimerAction::Pause
+                        | TimerAction::Skip
+                        | TimerAction::Restart
+                        | TimerAction::Extend(_)
+                        | TimerAction::Lap
+                        | TimerAction::Next),
+                    ) => {
+                        act_on(self.extras[self.selected - 1].as_mut(), action);
+                        true
+                    }
+                    _ => false,
+                };
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn submit_prompt(&mut self) {
+        let Some(input) = self.prompt.as_deref() else {
+            return;
+        };
+
+        match parse_new_timer(input) {
+            Ok((name, duration)) => {
+                self.extras
+                    .push(Box::new(TimerSession::new(duration, name)));
+                self.selected = self.extras.len();
+                self.prompt = None;
+                self.prompt_error = None;
+            }
+            Err(error) => {
+                self.prompt_error = Some(match error.suggestion(input) {
+                    Some(suggestion) => format!("did you mean `{}`?", suggestion),
+                    None => error.to_string(),
+                })
+            }
+        }
     }
 
     fn key_to_action(&self, key: KeyCode, modifiers: KeyModifiers) -> Option<TimerAction> {
@@ -192,23 +915,117 @@ impl App {
                 Some(TimerAction::Quit)
             }
             KeyCode::Char('q') => Some(TimerAction::Quit),
+            // Only the facilitator moves a shared standup on to the next speaker
+            KeyCode::Enter | KeyCode::Char(' ') => match &self.session_type {
+                SessionType::Shared(ws_handler) if ws_handler.role != Role::Host => None,
+                _ => Some(TimerAction::Next),
+            },
             KeyCode::Char('p') => Some(TimerAction::Pause),
+            KeyCode::Char('s') => Some(self.put_to_vote(TimerAction::Skip, Proposal::Skip)),
+            KeyCode::Char('r') => Some(self.put_to_vote(TimerAction::Restart, Proposal::Restart)),
+            KeyCode::Char('l') => Some(TimerAction::Lap),
+            KeyCode::Char('+') => Some(self.put_to_vote(
+                TimerAction::Extend(EXTEND_FOCUS_DURATION),
+                Proposal::Extend(EXTEND_FOCUS_DURATION),
+            )),
+            KeyCode::Char('S') => Some(TimerAction::Propose(Proposal::SkipBreak)),
+            KeyCode::Char('E') => Some(TimerAction::Propose(Proposal::ExtendFocus(
+                EXTEND_FOCUS_DURATION,
+            ))),
+            KeyCode::Char('N') => Some(TimerAction::Propose(Proposal::StartNextSession)),
+            KeyCode::Char('y') => Some(TimerAction::Vote(true)),
+            KeyCode::Char('n') => Some(TimerAction::Vote(false)),
             _ => None,
         }
     }
 
-    fn handle_action(&mut self, action: TimerAction) {
-        let timer = self.get_timer().unwrap();
+    /// In a shared session, changing the timer for everyone takes a vote like the other
+    /// proposals do.
+    fn put_to_vote(&self, action: TimerAction, proposal: Proposal) -> TimerAction {
+        match self.session_type {
+            // Extra sessions are local, there's nobody to ask
+            SessionType::Shared(_) if self.selected == 0 => TimerAction::Propose(proposal),
+            _ => action,
+        }
+    }
 
+    fn handle_action(&mut self, action: TimerAction, sender: SocketAddr) {
         match action {
             TimerAction::Quit => {
-                timer.set_status(TimerStatus::Exit);
+                if self.webhooks.is_some() {
+                    let info = self.get_timer().is_some().then(|| self.get_session_info());
+                    if let Some(webhooks) = &mut self.webhooks {
+                        webhooks.quit(info.as_ref());
+                    }
+                }
+                if let Some(standup) = self.session.get_standup() {
+                    standup.finish();
+                }
+                if let Some(timer) = self.get_timer() {
+                    timer.dismiss();
+                }
                 if let Some(pomodoro) = self.session.get_pomodoro() {
                     pomodoro.set_state(PomodoroState::Completed)
                 }
+                if let Some(mob) = self.session.get_mob() {
+                    mob.set_state(MobState::Completed)
+                }
+                if let Some(plan) = self.session.get_plan() {
+                    plan.finish();
+                }
+                if let Some(flowtime) = self.session.get_flowtime() {
+                    flowtime.finish();
+                }
+                self.extras.clear();
             }
-            TimerAction::Pause => {
-                self.session.toggle_pause();
+            TimerAction::Pause
+            | TimerAction::Skip
+            | TimerAction::Restart
+            | TimerAction::Lap
+            | TimerAction::Extend(_)
+            | TimerAction::Next => act_on(self.session.as_mut(), action),
+            TimerAction::Propose(proposal) => match self.session_type {
+                // Nobody else to ask when running alone
+                SessionType::SingleUser => self.apply_proposal(proposal),
+                SessionType::Shared(_) => {
+                    if self.ballot.is_none() {
+                        self.ballot = Some(Ballot::new(proposal, sender));
+                    }
+                }
+            },
+            TimerAction::Vote(approve) => {
+                if let Some(ballot) = &mut self.ballot {
+                    ballot.cast(sender, approve);
+                }
+            }
+            // Peers keep their own timers, the status is only for clients that don't
+            TimerAction::Sync(_) => {}
+            TimerAction::Resolve(passed) => {
+                if let Some(ballot) = self.ballot.take() {
+                    if passed {
+                        self.apply_proposal(ballot.get_proposal());
+                    }
+                }
+            }
+        }
+    }
+
+    fn apply_proposal(&mut self, proposal: Proposal) {
+        let session = self.session.as_mut();
+        match proposal {
+            Proposal::Skip => act_on(session, TimerAction::Skip),
+            Proposal::Restart => act_on(session, TimerAction::Restart),
+            Proposal::Extend(duration) => act_on(session, TimerAction::Extend(duration)),
+            Proposal::SkipBreak | Proposal::ExtendFocus(_) | Proposal::StartNextSession => {
+                let Some(pomodoro) = session.get_pomodoro() else {
+                    return;
+                };
+                match proposal {
+                    Proposal::SkipBreak => pomodoro.skip_break(),
+                    Proposal::ExtendFocus(duration) => pomodoro.extend_focus(duration),
+                    Proposal::StartNextSession => pomodoro.start_next_session(),
+                    Proposal::Skip | Proposal::Restart | Proposal::Extend(_) => {}
+                }
             }
         }
     }
@@ -218,16 +1035,28 @@ impl App {
     }
 
     pub fn get_session_info(&mut self) -> SessionInfo {
+        let mut info = SessionInfo {
+            timer: self.get_timer_info(),
+            ..SessionInfo::default()
+        };
         match self.mode {
-            Mode::Timer => SessionInfo {
-                timer: self.get_timer_info(),
-                pomodoro: None,
-            },
-            Mode::Pomodoro => SessionInfo {
-                timer: self.get_timer_info(),
-                pomodoro: self.get_pomodoro_info(),
-            },
+            Mode::Timer => {}
+            Mode::Pomodoro => info.pomodoro = self.get_pomodoro_info(),
+            Mode::Mob => info.mob = self.get_mob_info(),
+            Mode::Standup => info.standup = self.get_standup_info(),
+            Mode::Stopwatch => info.stopwatch = self.get_stopwatch_info(),
+            Mode::Plan => info.plan = self.get_plan_info(),
+            Mode::Flowtime => {
+                info.pomodoro = self.get_flowtime_pomodoro_info();
+                info.flowtime = self.get_flowtime_info();
+            }
         }
+        info
+    }
+
+    pub fn get_session_status(&mut self) -> Option<SessionStatus> {
+        self.get_timer()?;
+        Some(SessionStatus::from(&self.get_session_info()))
     }
 
     fn get_timer_info(&mut self) -> TimerInfo {
@@ -235,15 +1064,500 @@ impl App {
             .get_timer()
             .map(|timer| TimerInfo {
                 name: timer.get_name().to_string(),
-                remaining: timer.remaining_time(),
+                // A timer counting up has no end to report
+                remaining: match timer.counts_up() {
+                    true => Duration::ZERO,
+                    false => timer.remaining_time(),
+                },
+                duration: match timer.counts_up() {
+                    true => Duration::ZERO,
+                    false => timer.get_duration(),
+                },
+                paused: timer.is_paused(),
+                deadline: timer.snapshot().deadline,
+                overrun: timer.overrun(),
             })
-            .unwrap()
+            // A pomodoro past its last break has no timer left
+            .unwrap_or_default()
     }
 
     fn get_pomodoro_info(&mut self) -> Option<PomodoroInfo> {
         self.session.get_pomodoro().map(|pomodoro| PomodoroInfo {
+            state: pomodoro.get_state(),
             total_sessions: pomodoro.get_total_sessions(),
+            long_break_every: pomodoro.get_long_break_every(),
+            set_position: pomodoro.get_set_position(),
+            completed_today: pomodoro.get_completed(),
             current_session: pomodoro.get_current_session(),
+            pending: pomodoro.get_pending(),
+            waited: pomodoro.get_waited(),
+            overrun: pomodoro.get_overrun(),
+            next_phase: pomodoro.get_next_phase().map(|(phase, _)| phase),
+            next_duration: pomodoro
+                .get_next_phase()
+                .map_or(Duration::ZERO, |(_, duration)| duration),
+        })
+    }
+
+    // Flowtime passes through the same phases, so whatever follows a pomodoro follows it too
+    fn get_flowtime_pomodoro_info(&mut self) -> Option<PomodoroInfo> {
+        self.session.get_flowtime().map(|flowtime| PomodoroInfo {
+            state: flowtime.get_state(),
+            total_sessions: flowtime.get_total_sessions(),
+            long_break_every: 0,
+            set_position: flowtime.get_current_session(),
+            completed_today: flowtime.get_completed(),
+            current_session: flowtime.get_current_session(),
+            pending: None,
+            waited: Duration::ZERO,
+            overrun: Duration::ZERO,
+            next_phase: None,
+            next_duration: Duration::ZERO,
+        })
+    }
+
+    fn get_flowtime_info(&mut self) -> Option<FlowtimeInfo> {
+        self.session.get_flowtime().map(|flowtime| FlowtimeInfo {
+            focused: flowtime.get_focused(),
+            last_focus: flowtime.get_last_focus(),
+            running_focus: flowtime.get_running_focus(),
+            earned_break: flowtime.get_earned_break(),
+        })
+    }
+
+    fn get_mob_info(&mut self) -> Option<MobInfo> {
+        self.session.get_mob().map(|mob| MobInfo {
+            driver: mob.get_driver().map(str::to_string),
+            next_driver: mob.get_next_driver().map(str::to_string),
+            rotation: mob.get_rotation(),
         })
     }
+
+    fn get_standup_info(&mut self) -> Option<StandupInfo> {
+        self.session.get_standup().map(|standup| StandupInfo {
+            current_speaker: standup.get_speaker_index() + 1,
+            total_speakers: standup.get_total_speakers(),
+            next_speaker: standup.get_next_speaker().map(str::to_string),
+            overrun: standup.get_overrun(),
+        })
+    }
+
+    fn get_stopwatch_info(&mut self) -> Option<StopwatchInfo> {
+        self.session.get_stopwatch().map(|stopwatch| StopwatchInfo {
+            elapsed: stopwatch.elapsed_time(),
+            laps: stopwatch.get_laps().to_vec(),
+        })
+    }
+
+    fn get_plan_info(&mut self) -> Option<PlanInfo> {
+        self.session.get_plan().map(|plan| PlanInfo {
+            title: plan.get_title().map(str::to_string),
+            current_step: plan.get_current() + 1,
+            total_steps: plan.get_steps().len(),
+            waiting: plan.is_waiting(),
+            upcoming: plan.get_upcoming().to_vec(),
+            remaining: plan.total_remaining(),
+        })
+    }
+
+    /// Writes the stopwatch's laps to `path` once the app has finished.
+    pub fn export_laps(&mut self, path: &std::path::Path) -> io::Result<()> {
+        match self.session.get_stopwatch() {
+            Some(stopwatch) => stopwatch.export(path),
+            None => Ok(()),
+        }
+    }
+
+    /// How far the timer or pomodoro ran into overtime, if it did.
+    pub fn get_overrun(&mut self) -> Option<Duration> {
+        let overrun = match self.session.get_pomodoro() {
+            Some(pomodoro) => pomodoro.get_overrun(),
+            None => self.get_timer()?.overrun(),
+        };
+        (!overrun.is_zero()).then_some(overrun)
+    }
+
+    /// The end-of-standup report, printed once the terminal has been restored.
+    pub fn get_standup_summary(&mut self) -> Option<String> {
+        self.session
+            .get_standup()
+            .map(|standup| standup.summary().to_string())
+    }
+
+    /// Every session with its gauge, once there is more than the main one.
+    pub fn get_session_entries(&mut self) -> Vec<SessionEntry> {
+        if self.extras.is_empty() {
+            return Vec::new();
+        }
+
+        let selected = self.selected;
+        std::iter::once(self.session.as_mut())
+            .chain(self.extras.iter_mut().map(|session| session.as_mut()))
+            .enumerate()
+            .map(|(index, session)| {
+                let name = session_name(session);
+                let selected = index == selected;
+                match session.get_timer() {
+                    Some(timer) => SessionEntry {
+                        name,
+                        label: timer.format_signed_duration(timer.signed_remaining_secs()),
+                        // A zero-length timer is as done as it gets
+                        ratio: match timer.get_duration().is_zero() {
+                            true => 1.0,
+                            false => (timer.elapsed_time().as_secs_f64()
+                                / timer.get_duration().as_secs_f64())
+                            .min(1.0),
+                        },
+                        paused: timer.is_paused(),
+                        overtime: timer.is_overtime(),
+                        selected,
+                    },
+                    None => SessionEntry {
+                        name,
+                        label: "Done".to_string(),
+                        ratio: 1.0,
+                        paused: false,
+                        overtime: false,
+                        selected,
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// Whether an extra session rather than the main one is selected.
+    pub fn is_extra_selected(&self) -> bool {
+        self.selected > 0
+    }
+
+    /// The new timer being typed and what was wrong with the last attempt, while the prompt is open.
+    pub fn get_prompt(&self) -> Option<(&str, Option<&str>)> {
+        self.prompt
+            .as_deref()
+            .map(|prompt| (prompt, self.prompt_error.as_deref()))
+    }
+
+    pub fn get_notice(&self) -> Option<&str> {
+        self.notice
+            .as_ref()
+            .filter(|(_, shown_at)| shown_at.elapsed() < NOTICE_DURATION)
+            .map(|(notice, _)| notice.as_str())
+    }
+
+    pub fn get_vote_info(&self) -> Option<VoteInfo> {
+        self.ballot.as_ref().map(|ballot| VoteInfo {
+            proposal: ballot.get_proposal(),
+            yes: ballot.yes(),
+            no: ballot.no(),
+        })
+    }
+}
+
+/// Applies an action that only concerns one session, not the app or the peers.
+fn act_on(session: &mut dyn Session, action: TimerAction) {
+    match action {
+        TimerAction::Pause => session.toggle_pause(),
+        TimerAction::Skip => {
+            if let Some(pomodoro) = session.get_pomodoro() {
+                pomodoro.skip_phase();
+            } else if let Some(mob) = session.get_mob() {
+                mob.skip_turn();
+            } else if let Some(standup) = session.get_standup() {
+                standup.next_speaker();
+            } else if let Some(plan) = session.get_plan() {
+                plan.skip();
+            } else if let Some(flowtime) = session.get_flowtime() {
+                flowtime.next_phase();
+            } else if session.get_stopwatch().is_some() {
+                // There's no phase to skip, and stopping it is what `q` is for
+            } else if let Some(timer) = session.get_timer() {
+                timer.dismiss();
+            }
+        }
+        TimerAction::Restart => {
+            if let Some(stopwatch) = session.get_stopwatch() {
+                stopwatch.reset();
+            } else if let Some(timer) = session.get_timer() {
+                timer.restart();
+            }
+        }
+        TimerAction::Lap => {
+            if let Some(stopwatch) = session.get_stopwatch() {
+                stopwatch.lap();
+            }
+        }
+        TimerAction::Extend(duration) => {
+            if let Some(timer) = session.get_timer() {
+                timer.extend(duration);
+            }
+        }
+        TimerAction::Next => {
+            if let Some(standup) = session.get_standup() {
+                standup.next_speaker();
+            } else if let Some(pomodoro) = session.get_pomodoro() {
+                match pomodoro.is_overtime() {
+                    true => pomodoro.end_overtime(),
+                    false => pomodoro.start_pending(),
+                }
+            } else if let Some(plan) = session.get_plan() {
+                plan.next();
+            } else if let Some(flowtime) = session.get_flowtime() {
+                flowtime.next_phase();
+            } else if let Some(timer) = session.get_timer() {
+                if timer.is_overtime() {
+                    timer.dismiss();
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn session_name(session: &mut dyn Session) -> String {
+    if session.get_pomodoro().is_some() {
+        return "Pomodoro".to_string();
+    }
+    if session.get_flowtime().is_some() {
+        return "Flowtime".to_string();
+    }
+    session.get_timer().map_or_else(
+        || "Session".to_string(),
+        |timer| timer.get_name().to_string(),
+    )
+}
+
+/// Reads a new timer typed as `tea 4m`, `build 1h 30m` or `call @10:00`.
+fn parse_new_timer(input: &str) -> Result<(String, Duration), ParseTimeError> {
+    let (name, duration) = match input.rsplit_once('@') {
+        Some((name, time)) => {
+            let time = parse_time_of_day(time).map_err(|error| error.offset(name.len() + 1))?;
+            (name, clock::time_until(time, &Local::now()))
+        }
+        None => parse_named_duration(input)?,
+    };
+
+    let name = match name.trim() {
+        "" => "Timer",
+        name => name,
+    };
+    Ok((name.to_string(), duration))
+}
+
+async fn recv_api_command(
+    receiver: Option<flume::Receiver<ApiCommand>>,
+) -> Result<ApiCommand, flume::RecvError> {
+    match receiver {
+        Some(receiver) => receiver.recv_async().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn manual_pomodoro() -> (Pomodoro, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock::new());
+        let pomodoro = Pomodoro::builder()
+            .sessions(1)
+            .focus(MINUTE)
+            .short_break(MINUTE)
+            .clock(clock.clone())
+            .build();
+        (pomodoro, clock)
+    }
+
+    #[tokio::test]
+    async fn test_webhook_on_completed_pomodoro() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        let (bodies, received) = flume::unbounded();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = vec![0; 4096];
+                let n = socket.read(&mut request).await.unwrap();
+                let _ = bodies.send(String::from_utf8_lossy(&request[..n]).to_string());
+                let _ = socket.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").await;
+            }
+        });
+
+        let (pomodoro, clock) = manual_pomodoro();
+        let mut app = App::new_pomodoro(pomodoro, Duration::from_secs(1));
+        app.set_webhooks(WebhookNotifier::new(vec![url], None));
+
+        app.publish_status();
+        for _ in 0..2 {
+            clock.advance(MINUTE);
+            app.tick_sessions();
+            app.publish_status();
+        }
+        assert!(app.get_timer().is_none());
+        assert!(app.should_quit());
+
+        app.webhooks.take().unwrap().finish().await;
+        let events: Vec<String> = received.try_iter().collect();
+        assert_eq!(events.len(), 4);
+        assert!(events[3].contains(r#""event":"cycle_completed""#));
+    }
+
+    #[test]
+    fn test_shared_actions_take_a_vote() {
+        let local = SocketAddr::from(([127, 0, 0, 1], 1));
+        let (pomodoro, _) = manual_pomodoro();
+        let (mut app, _ws_handler) = App::new_shared_pomodoro(
+            pomodoro,
+            Duration::from_secs(1),
+            Role::Host,
+            VoteRule::Majority,
+        );
+
+        let action = app.key_to_action(KeyCode::Char('s'), KeyModifiers::NONE);
+        assert!(matches!(action, Some(TimerAction::Propose(Proposal::Skip))));
+
+        app.handle_action(action.unwrap(), local);
+        assert_eq!(
+            app.session.get_pomodoro().unwrap().get_state(),
+            PomodoroState::Focus(1)
+        );
+
+        app.handle_action(TimerAction::Resolve(true), local);
+        assert_eq!(
+            app.session.get_pomodoro().unwrap().get_state(),
+            PomodoroState::Break(1)
+        );
+
+        // An extra timer is only ours to skip
+        app.extras
+            .push(Box::new(TimerSession::new(MINUTE, "Tea".to_string())));
+        app.selected = 1;
+        assert!(app.handle_local_key(KeyEvent::from(KeyCode::Char('s'))));
+        assert!(app.extras[0].is_finished());
+        assert!(app.ballot.is_none());
+
+        let (pomodoro, _) = manual_pomodoro();
+        let app = App::new_pomodoro(pomodoro, Duration::from_secs(1));
+        assert!(matches!(
+            app.key_to_action(KeyCode::Char('s'), KeyModifiers::NONE),
+            Some(TimerAction::Skip)
+        ));
+    }
+
+    #[test]
+    fn test_stopwatch_has_no_end() {
+        let mut app = App::new_stopwatch(Duration::from_secs(1));
+
+        let info = app.get_session_info();
+        assert_eq!(info.timer.remaining, Duration::ZERO);
+        assert_eq!(info.timer.duration, Duration::ZERO);
+
+        app.handle_action(TimerAction::Skip, SocketAddr::from(([127, 0, 0, 1], 1)));
+        app.tick_sessions();
+        assert!(!app.should_quit());
+    }
+
+    fn type_keys(app: &mut App, text: &str) {
+        for c in text.chars() {
+            app.handle_local_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+    }
+
+    #[test]
+    fn test_add_and_remove_extras() {
+        let mut app = App::new_timer(MINUTE, "Focus".to_string(), Duration::from_secs(1));
+
+        type_keys(&mut app, "atea 4m");
+        // Ctrl+C quits rather than being typed into the prompt
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert!(!app.handle_local_key(ctrl_c));
+        assert_eq!(app.prompt.as_deref(), Some("tea 4m"));
+        assert!(matches!(
+            app.key_to_action(ctrl_c.code, ctrl_c.modifiers),
+            Some(TimerAction::Quit)
+        ));
+
+        app.handle_local_key(KeyEvent::from(KeyCode::Enter));
+        type_keys(&mut app, "awalk 10");
+        app.handle_local_key(KeyEvent::from(KeyCode::Enter));
+        assert!(app.prompt.is_none());
+        assert_eq!(app.extras.len(), 2);
+        assert_eq!(session_name(app.extras[0].as_mut()), "tea");
+        assert_eq!(app.selected, 2);
+
+        app.handle_local_key(KeyEvent::from(KeyCode::Tab));
+        assert_eq!(app.selected, 0);
+        app.handle_local_key(KeyEvent::from(KeyCode::Up));
+        assert_eq!(app.selected, 2);
+
+        // A bad duration keeps the prompt open with a hint
+        type_keys(&mut app, "anap 4mm");
+        app.handle_local_key(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(app.prompt_error.as_deref(), Some("did you mean `nap 4m`?"));
+        app.handle_local_key(KeyEvent::from(KeyCode::Esc));
+        assert!(app.prompt.is_none());
+
+        // Removing the last one moves the selection up, the main session can't be removed
+        type_keys(&mut app, "x");
+        assert_eq!(app.extras.len(), 1);
+        assert_eq!(app.selected, 1);
+        type_keys(&mut app, "x");
+        assert!(app.extras.is_empty());
+        assert_eq!(app.selected, 0);
+        assert!(!app.handle_local_key(KeyEvent::from(KeyCode::Char('x'))));
+    }
+
+    #[test]
+    fn test_deselect() {
+        let mut app = App::new_timer(MINUTE, "Focus".to_string(), Duration::from_secs(1));
+        for name in ["a", "b", "c"] {
+            app.extras
+                .push(Box::new(TimerSession::new(MINUTE, name.to_string())));
+        }
+
+        // The selection stays on `c` when `a` goes away before it
+        app.selected = 3;
+        app.extras.remove(0);
+        app.deselect(1);
+        assert_eq!(app.selected, 2);
+        assert_eq!(session_name(app.extras[app.selected - 1].as_mut()), "c");
+
+        // And on `b` when `c` goes away after it
+        app.selected = 1;
+        app.extras.remove(1);
+        app.deselect(2);
+        assert_eq!(app.selected, 1);
+
+        // Removing the selected one when it was the last moves back
+        app.extras.remove(0);
+        app.deselect(1);
+        assert_eq!(app.selected, 0);
+    }
+
+    #[test]
+    fn test_parse_new_timer() {
+        assert_eq!(
+            parse_new_timer("tea 4m").unwrap(),
+            ("tea".to_string(), 4 * MINUTE)
+        );
+        assert_eq!(
+            parse_new_timer("90").unwrap(),
+            ("Timer".to_string(), 90 * MINUTE)
+        );
+
+        let (name, duration) = parse_new_timer("lunch @ 12:30").unwrap();
+        assert_eq!(name, "lunch");
+        assert!(duration <= 24 * 60 * MINUTE);
+
+        // Errors point into the whole input
+        assert_eq!(parse_new_timer("tea 4mm").unwrap_err().span(), 5..7);
+        assert_eq!(parse_new_timer("lunch@25:00").unwrap_err().span(), 6..11);
+    }
 }
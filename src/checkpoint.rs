@@ -0,0 +1,252 @@
+use crate::paths;
+use crate::session::SessionSnapshot;
+use crate::timer::TimerStatus;
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+fn checkpoint_path() -> PathBuf {
+    paths::state_dir().join(CHECKPOINT_FILE)
+}
+
+/// Everything needed to pick a single-user timer or pomodoro back up after a crash.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub session: SessionSnapshot,
+    pub task: Option<String>,
+    pub saved_at: u64,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl Checkpoint {
+    pub fn new(session: SessionSnapshot) -> Self {
+        Checkpoint {
+            session,
+            task: None,
+            saved_at: unix_now(),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.session
+            .get_timer()
+            .is_some_and(|timer| timer.status == TimerStatus::Paused)
+    }
+
+    pub fn load() -> io::Result<Option<Checkpoint>> {
+        match fs::read_to_string(checkpoint_path()) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map(Some)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Time that passed since the checkpoint was written.
+    pub fn downtime(&self) -> Duration {
+        Duration::from_secs(unix_now().saturating_sub(self.saved_at))
+    }
+
+    /// The saved session as it would be now: a running timer kept running while we were down.
+    /// One counting up, like a flowtime focus, only counts the time we were up.
+    pub fn snapshot_by_now(&self) -> SessionSnapshot {
+        let mut session = self.session.clone();
+        if let Some(timer) = session.get_timer_mut() {
+            if timer.status == TimerStatus::Running && !timer.counts_up() {
+                timer.elapsed += self.downtime();
+            }
+        }
+        session
+    }
+}
+
+impl fmt::Display for Checkpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let minutes = |duration: Duration| duration.as_secs().div_ceil(60);
+
+        match &self.session {
+            SessionSnapshot::Timer(timer) => write!(
+                f,
+                "timer `{}` with {} min left",
+                timer.name,
+                minutes(timer.duration.saturating_sub(timer.elapsed))
+            )?,
+            SessionSnapshot::Pomodoro(pomodoro) if pomodoro.total_sessions == 0 => write!(
+                f,
+                "continuous pomodoro in {:?}, {} done",
+                pomodoro.state, pomodoro.completed
+            )?,
+            SessionSnapshot::Pomodoro(pomodoro) => write!(
+                f,
+                "pomodoro in {:?} of {} sessions",
+                pomodoro.state, pomodoro.total_sessions
+            )?,
+            SessionSnapshot::Mob(_) => write!(f, "mob rotation")?,
+            SessionSnapshot::Standup(_) => write!(f, "standup")?,
+            SessionSnapshot::Stopwatch(_) => write!(f, "stopwatch")?,
+            SessionSnapshot::Flowtime(flowtime) => write!(
+                f,
+                "flowtime in {:?}, {} done",
+                flowtime.state, flowtime.completed
+            )?,
+            SessionSnapshot::Plan(_) => write!(f, "plan")?,
+        }
+
+        if let Some(task) = &self.task {
+            write!(f, " on `{}`", task)?;
+        }
+        if self.is_paused() {
+            write!(f, ", paused")?;
+        }
+        write!(f, ", saved {} min ago", self.downtime().as_secs() / 60)
+    }
+}
+
+/// Writes checkpoints every few seconds, and right away when the pause state flips.
+pub struct Checkpointer {
+    path: PathBuf,
+    task: Option<String>,
+    last_saved: Option<(Instant, bool)>,
+}
+
+impl Checkpointer {
+    pub fn new(task: Option<String>) -> Self {
+        Checkpointer {
+            path: checkpoint_path(),
+            task,
+            last_saved: None,
+        }
+    }
+
+    pub fn save_if_due(&mut self, checkpoint: Checkpoint) {
+        let due = match self.last_saved {
+            Some((at, paused)) => {
+                at.elapsed() >= CHECKPOINT_INTERVAL || paused != checkpoint.is_paused()
+            }
+            None => true,
+        };
+        if !due {
+            return;
+        }
+
+        let paused = checkpoint.is_paused();
+        let checkpoint = Checkpoint {
+            task: self.task.clone(),
+            ..checkpoint
+        };
+
+        match self.write(&checkpoint) {
+            Ok(()) => self.last_saved = Some((Instant::now(), paused)),
+            Err(e) => debug!("CHECKPOINT: Failed to save {:?}: {:?}", self.path, e),
+        }
+    }
+
+    /// Writes to a temporary file first, so a crash mid-write never leaves a torn checkpoint.
+    fn write(&self, checkpoint: &Checkpoint) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temporary = self.path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_string(checkpoint)?)?;
+        fs::rename(&temporary, &self.path)
+    }
+
+    pub fn clear(&self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            if e.kind() != io::ErrorKind::NotFound {
+                debug!("CHECKPOINT: Failed to remove {:?}: {:?}", self.path, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::flowtime::{BreakRule, Flowtime};
+    use crate::pomodoro::{AutoStart, PomodoroSnapshot, PomodoroState};
+    use crate::timer::TimerSnapshot;
+    use std::sync::Arc;
+
+    fn timer_snapshot(status: TimerStatus) -> SessionSnapshot {
+        SessionSnapshot::Timer(TimerSnapshot {
+            name: "Tea".to_string(),
+            duration: Duration::from_secs(300),
+            elapsed: Duration::from_secs(60),
+            status,
+            deadline: None,
+            overtime: false,
+        })
+    }
+
+    #[test]
+    fn test_snapshot_by_now() {
+        let mut checkpoint = Checkpoint::new(timer_snapshot(TimerStatus::Running));
+        checkpoint.saved_at -= 120;
+
+        assert_eq!(checkpoint.downtime(), Duration::from_secs(120));
+        assert_eq!(
+            checkpoint.snapshot_by_now().get_timer().unwrap().elapsed,
+            Duration::from_secs(180)
+        );
+        assert_eq!(
+            checkpoint.to_string(),
+            "timer `Tea` with 4 min left, saved 2 min ago"
+        );
+
+        checkpoint.session = timer_snapshot(TimerStatus::Paused);
+        assert!(checkpoint.is_paused());
+        assert_eq!(checkpoint.snapshot_by_now(), checkpoint.session);
+
+        // A focus counting up doesn't go on while we're down
+        let flowtime = Flowtime::new(BreakRule::default(), 0, Arc::new(ManualClock::new()));
+        checkpoint.session = SessionSnapshot::Flowtime(flowtime.snapshot());
+        assert!(!checkpoint.is_paused());
+        assert_eq!(checkpoint.snapshot_by_now(), checkpoint.session);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let checkpoint = Checkpoint {
+            task: Some("Write report".to_string()),
+            ..Checkpoint::new(SessionSnapshot::Pomodoro(PomodoroSnapshot {
+                state: PomodoroState::Break(2),
+                total_sessions: 4,
+                long_break_every: 4,
+                completed: 1,
+                focus_duration: Duration::from_secs(25 * 60).into(),
+                break_duration: Duration::from_secs(5 * 60).into(),
+                long_break_duration: Duration::from_secs(15 * 60),
+                timer: None,
+                pending: None,
+                waited: Duration::ZERO,
+                auto_start: AutoStart::default(),
+                ends_at: None,
+                overtime: false,
+                overrun: Duration::ZERO,
+            }))
+        };
+
+        let json = serde_json::to_string(&checkpoint).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Checkpoint>(&json).unwrap(),
+            checkpoint
+        );
+    }
+}
@@ -1,5 +1,14 @@
-use crate::parser::parse_duration;
-use clap::{Parser, Subcommand};
+use chrono::NaiveTime;
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use pomoduro::flowtime::Tier;
+use pomoduro::parser::{parse_duration, parse_time_of_day, Diagnostic};
+use pomoduro::standup::Speaker;
+use pomoduro::timer::SuspendPolicy;
+use pomoduro::vote::VoteRule;
+use pomoduro::webhook::WebhookUrl;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::time::Duration;
 
 #[derive(Parser)]
@@ -9,34 +18,151 @@ use std::time::Duration;
 pub struct Cli {
     #[command(subcommand)]
     pub command: Option<Commands>,
+    /// Serve a local HTTP API and event stream for the running session, e.g. 127.0.0.1:7070
+    #[arg(long, global = true)]
+    pub http: Option<SocketAddr>,
+    /// POST phase transitions as JSON to this URL; may be given more than once
+    #[arg(long = "webhook", global = true)]
+    pub webhooks: Vec<WebhookUrl>,
+    /// What to do with time spent suspended; shared sessions always count it
+    #[arg(long, global = true, value_enum, default_value_t = SuspendPolicy::Count)]
+    pub on_suspend: SuspendPolicy,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     #[command(about = "Start a timer", visible_alias = "t")]
     Timer {
-        #[arg(value_parser = parse_duration, short, long)]
-        duration: Duration,
+        #[arg(value_parser = duration_arg, short, long, required_unless_present = "until")]
+        duration: Option<Duration>,
+        /// Run until this local time of day instead, e.g. 15:30; tomorrow if it already passed
+        #[arg(short, long, value_parser = time_of_day_arg, conflicts_with = "duration")]
+        until: Option<NaiveTime>,
         #[arg(short, long)]
         name: Option<String>,
+        /// Keep counting past zero until dismissed with [Enter] instead of quitting
+        #[arg(long)]
+        overtime: bool,
     },
 
     #[command(about = "Start a pomodoro session", visible_alias = "p")]
     Pomodoro {
-        #[arg(short, long)]
+        /// Stop after this many focus sessions, the target for the day
+        #[arg(short, long, conflicts_with = "continuous")]
         sessions: Option<usize>,
-        #[arg(short, long="focus", value_parser = parse_duration)]
-        focus_duration: Option<Duration>,
-        #[arg(short, long="break", value_parser = parse_duration)]
-        break_duration: Option<Duration>,
-        #[arg(short, long="long", value_parser = parse_duration)]
+        /// Keep cycling until stopped instead of stopping at a target
+        #[arg(long)]
+        continuous: bool,
+        /// Fit as many sessions as possible before this local time of day, e.g. 15:30
+        #[arg(short, long, value_parser = time_of_day_arg, conflicts_with_all = ["sessions", "continuous"])]
+        until: Option<NaiveTime>,
+        /// Take a long break after every this many focus sessions, 0 never does
+        #[arg(short = 'e', long)]
+        long_break_every: Option<usize>,
+        /// Focus length, or one per session like `15m,20m,25m` with the last repeating
+        #[arg(short, long="focus", value_parser = duration_arg, value_delimiter = ',')]
+        focus_duration: Vec<Duration>,
+        /// Make each focus session this much longer than the one before, starting from `--focus`
+        #[arg(long, value_parser = duration_arg)]
+        focus_step: Option<Duration>,
+        /// Stop growing focus sessions at this length
+        #[arg(long, value_parser = duration_arg, requires = "focus_step")]
+        focus_max: Option<Duration>,
+        /// Break length, or one per session like `3m,4m,5m` with the last repeating
+        #[arg(short, long="break", value_parser = duration_arg, value_delimiter = ',')]
+        break_duration: Vec<Duration>,
+        /// Make each break this much longer than the one before, starting from `--break`
+        #[arg(long, value_parser = duration_arg)]
+        break_step: Option<Duration>,
+        /// Stop growing breaks at this length
+        #[arg(long, value_parser = duration_arg, requires = "break_step")]
+        break_max: Option<Duration>,
+        #[arg(short, long="long", value_parser = duration_arg)]
         long_break_duration: Option<Duration>,
+        #[arg(long)]
+        task: Option<String>,
+        /// Wait for a key press before each focus phase instead of starting it right away
+        #[arg(long)]
+        manual_focus: bool,
+        /// Wait for a key press before each break instead of starting it right away
+        #[arg(long)]
+        manual_breaks: bool,
+        /// Keep each phase counting past zero until dismissed with [Enter] instead of moving on
+        #[arg(long)]
+        overtime: bool,
+    },
+
+    #[command(
+        about = "Start a flowtime session: focus until you stop, then take a break in proportion",
+        visible_alias = "f"
+    )]
+    Flowtime {
+        /// Stop after this many focus sessions, 0 keeps cycling until stopped
+        #[arg(short, long, default_value_t = 0)]
+        sessions: usize,
+        /// Breaks last this fraction of the focus before them, e.g. 5 for a fifth
+        #[arg(long, default_value_t = 5, conflicts_with = "tiers")]
+        break_fraction: u32,
+        /// Breaks by focus length as `up_to:break`, the last may be just `break`, e.g. `25m:5m,50m:8m,10m`
+        #[arg(long, value_delimiter = ',', value_parser = tier_arg)]
+        tiers: Vec<Tier>,
+        #[arg(long)]
+        task: Option<String>,
+    },
+
+    #[command(
+        about = "Start a stopwatch that counts up with laps",
+        visible_alias = "w"
+    )]
+    Stopwatch {
+        /// Write the laps to this file on exit, as JSON if it ends in `.json` and CSV otherwise
+        #[arg(short, long)]
+        export: Option<PathBuf>,
+    },
+
+    #[command(about = "Start a mob programming rotation", visible_alias = "m")]
+    Mob {
+        #[arg(required = true, value_delimiter = ',')]
+        drivers: Vec<String>,
+        #[command(flatten)]
+        rotation: RotationArgs,
+    },
+
+    #[command(about = "Start a standup speaker timer", visible_alias = "s")]
+    Standup {
+        /// Speakers as `name` or `name:timebox`, e.g. `alice:2m,bob`
+        #[arg(required = true, value_delimiter = ',', value_parser = speaker_arg)]
+        speakers: Vec<Speaker>,
+        #[arg(short, long, value_parser = duration_arg)]
+        timebox: Option<Duration>,
+    },
+
+    #[command(
+        about = "Run a plan of timers from a TOML or YAML file",
+        visible_alias = "r"
+    )]
+    Run {
+        /// Steps with a `name`, a `duration` and optionally `repeat` and `advance = "manual"`
+        plan: PathBuf,
     },
 
     #[command(about = "Host a shared pomodoro session", visible_alias = "h")]
     Host {
         #[arg(short, long)]
         port: Option<u16>,
+        #[arg(long, value_enum, default_value_t = VoteRule::Majority)]
+        vote_rule: VoteRule,
+        /// Room the team statistics of this session are recorded under
+        #[arg(short, long, default_value = "default")]
+        room: String,
+        /// Name shown to the other participants, defaults to $USER
+        #[arg(short, long)]
+        name: Option<String>,
+        /// Let browser clients on the served page pause and resume the session
+        #[arg(long)]
+        browser_control: bool,
+        #[command(flatten)]
+        mode: SharedModeArgs,
     },
 
     #[command(about = "Join a shared pomodoro session", visible_alias = "j")]
@@ -45,9 +171,94 @@ pub enum Commands {
         address: Option<String>,
         #[arg(short, long)]
         port: Option<u16>,
+        /// Name shown to the other participants, defaults to $USER
+        #[arg(short, long)]
+        name: Option<String>,
+        #[command(flatten)]
+        mode: SharedModeArgs,
     },
+
+    #[command(about = "Resume the timer or pomodoro that was interrupted")]
+    Resume,
+
+    #[command(about = "Show team statistics recorded by a host")]
+    Stats {
+        #[arg(short, long, default_value = "default")]
+        room: String,
+    },
+}
+
+#[derive(Args)]
+pub struct RotationArgs {
+    #[arg(short, long = "turn", value_parser = duration_arg)]
+    pub turn_duration: Option<Duration>,
+    #[arg(short, long = "break", value_parser = duration_arg)]
+    pub break_duration: Option<Duration>,
+    /// Take a break after this many rotations, 0 never breaks
+    #[arg(short = 'e', long)]
+    pub breaks_every: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct SharedModeArgs {
+    /// Run a mob rotation between these drivers instead of a pomodoro, every peer must pass the same list
+    #[arg(long, value_delimiter = ',', conflicts_with = "standup")]
+    pub mob: Vec<String>,
+    #[command(flatten)]
+    pub rotation: RotationArgs,
+    /// Run a standup with these speakers instead of a pomodoro, every peer must pass the same list
+    #[arg(long, value_delimiter = ',', value_parser = speaker_arg)]
+    pub standup: Vec<Speaker>,
+    #[arg(long, value_parser = duration_arg)]
+    pub timebox: Option<Duration>,
+}
+
+// Parse errors point at the mistake in what was typed, so the flags render them as diagnostics
+fn duration_arg(input: &str) -> Result<Duration, Diagnostic> {
+    parse_duration(input).map_err(|error| Diagnostic::new(input, error))
+}
+
+fn time_of_day_arg(input: &str) -> Result<NaiveTime, Diagnostic> {
+    parse_time_of_day(input).map_err(|error| Diagnostic::new(input, error))
+}
+
+fn speaker_arg(input: &str) -> Result<Speaker, Diagnostic> {
+    input.parse().map_err(|error| Diagnostic::new(input, error))
+}
+
+fn tier_arg(input: &str) -> Result<Tier, Diagnostic> {
+    input.parse().map_err(|error| Diagnostic::new(input, error))
 }
 
 pub fn parse() -> Cli {
-    Cli::parse()
+    let cli = Cli::parse();
+    if let Some(Commands::Pomodoro {
+        focus_duration,
+        focus_step,
+        break_duration,
+        break_step,
+        ..
+    }) = &cli.command
+    {
+        // A ramp grows from one length, so a list would lose all but its first entry
+        if focus_step.is_some() && focus_duration.len() > 1 {
+            single_start("--focus-step", "--focus");
+        }
+        if break_step.is_some() && break_duration.len() > 1 {
+            single_start("--break-step", "--break");
+        }
+    }
+    cli
+}
+
+fn single_start(step: &str, lengths: &str) -> ! {
+    Cli::command()
+        .error(
+            ErrorKind::ArgumentConflict,
+            format!(
+                "{} starts from a single {} length, not a list",
+                step, lengths
+            ),
+        )
+        .exit()
 }
@@ -0,0 +1,187 @@
+use crate::timer::TimerAction;
+use crate::websocket::{relay, Role, WebSocketHandler};
+
+pub use crate::websocket::TimerMessage;
+
+use std::net::SocketAddr;
+use thiserror::Error;
+use tokio_tungstenite::tungstenite;
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("Failed to connect to the host: {0}")]
+    Connect(#[from] tungstenite::Error),
+
+    #[error("The connection to the host is closed")]
+    Closed,
+}
+
+/// Builds a `SessionClient`, e.g.
+/// `SessionClient::builder(addr).name("standup-bot").connect().await?`.
+pub struct SessionClientBuilder {
+    address: SocketAddr,
+    name: String,
+}
+
+impl SessionClientBuilder {
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub async fn connect(self) -> Result<SessionClient, ClientError> {
+        let handler = WebSocketHandler::new(Role::Guest);
+        let ws_stream = handler.connect(&self.address, self.name).await?;
+        let local_addr = handler.connected_from().await;
+
+        // The relay and the client each keep only their own ends of the channels, so each side
+        // sees the other hang up
+        let WebSocketHandler {
+            ws_to_app_sender,
+            ws_to_app_receiver: receiver,
+            app_to_ws_sender: sender,
+            app_to_ws_receiver,
+            ..
+        } = handler;
+        tokio::spawn(relay(
+            ws_stream,
+            local_addr,
+            ws_to_app_sender,
+            app_to_ws_receiver,
+        ));
+
+        Ok(SessionClient {
+            sender,
+            receiver,
+            local_addr,
+        })
+    }
+}
+
+/// A headless participant in a shared session: sends actions like any peer and receives
+/// everything the host relays, without running a session of its own.
+pub struct SessionClient {
+    sender: flume::Sender<TimerMessage>,
+    receiver: flume::Receiver<TimerMessage>,
+    local_addr: SocketAddr,
+}
+
+impl SessionClient {
+    pub fn builder(address: SocketAddr) -> SessionClientBuilder {
+        SessionClientBuilder {
+            address,
+            name: "pomoduro-client".to_string(),
+        }
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub async fn send(&self, action: TimerAction) -> Result<(), ClientError> {
+        let message = TimerMessage {
+            action,
+            sender: self.local_addr,
+        };
+
+        self.sender
+            .send_async(message)
+            .await
+            .map_err(|_| ClientError::Closed)
+    }
+
+    /// Waits for the next message relayed by the host, or `None` once the connection is gone.
+    pub async fn recv(&self) -> Option<TimerMessage> {
+        self.receiver.recv_async().await.ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vote::Proposal;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_clients_exchange_actions() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let host = WebSocketHandler::new(Role::Host);
+        tokio::spawn(async move { host.host(&addr, "host".to_string(), false).await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let alice = SessionClient::builder(addr)
+            .name("alice")
+            .connect()
+            .await
+            .unwrap();
+        let bob = SessionClient::builder(addr)
+            .name("bob")
+            .connect()
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        alice.send(TimerAction::Pause).await.unwrap();
+        let message = tokio::time::timeout(Duration::from_secs(2), bob.recv())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(matches!(message.action, TimerAction::Pause));
+        assert_eq!(message.sender, alice.local_addr());
+
+        // Changes to the timer are proposed rather than sent bare
+        let extend = Duration::from_secs(5 * 60);
+        bob.send(TimerAction::Propose(Proposal::Extend(extend)))
+            .await
+            .unwrap();
+        let message = tokio::time::timeout(Duration::from_secs(2), alice.recv())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(matches!(
+            message.action,
+            TimerAction::Propose(Proposal::Extend(duration)) if duration == extend
+        ));
+        assert_eq!(message.sender, bob.local_addr());
+    }
+
+    #[tokio::test]
+    async fn test_host_hangs_up() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let ws_stream = tokio_tungstenite::accept_async(socket).await.unwrap();
+            drop(ws_stream);
+        });
+
+        let client = SessionClient::builder(addr).connect().await.unwrap();
+        let message = tokio::time::timeout(Duration::from_secs(2), client.recv())
+            .await
+            .unwrap();
+
+        assert!(message.is_none());
+        assert!(matches!(
+            client.send(TimerAction::Pause).await,
+            Err(ClientError::Closed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_connect_error() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        assert!(matches!(
+            SessionClient::builder(addr).connect().await,
+            Err(ClientError::Connect(_))
+        ));
+    }
+}
@@ -0,0 +1,117 @@
+use chrono::{DateTime, Days, NaiveTime, TimeZone};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Where timers read the time from. Timers share one clock, so tests can drive them all by hand.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+    fn wall_now(&self) -> SystemTime;
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn wall_now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// Time from `now` until the next `time` of day in its time zone, rolling over to tomorrow if
+/// that time has already passed today.
+pub fn time_until<Tz: TimeZone>(time: NaiveTime, now: &DateTime<Tz>) -> Duration {
+    let today = now.date_naive();
+    let next = [today, today + Days::new(1)]
+        .into_iter()
+        .filter_map(|day| {
+            now.timezone()
+                .from_local_datetime(&day.and_time(time))
+                .earliest()
+        })
+        .find(|at| at > now);
+
+    next.and_then(|at| (at - now.clone()).to_std().ok())
+        .unwrap_or_default()
+}
+
+/// A clock that only moves when told to, for tests.
+pub struct ManualClock {
+    now: Mutex<(Instant, SystemTime)>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock {
+            now: Mutex::new((Instant::now(), SystemTime::now())),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap();
+        now.0 += duration;
+        now.1 += duration;
+    }
+
+    /// Moves only the wall clock, the way a system suspend looks to the process.
+    pub fn suspend(&self, duration: Duration) {
+        self.now.lock().unwrap().1 += duration;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        ManualClock::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.now.lock().unwrap().0
+    }
+
+    fn wall_now(&self) -> SystemTime {
+        self.now.lock().unwrap().1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, Utc};
+
+    #[test]
+    fn test_time_until() {
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 14, 10, 30).unwrap();
+
+        assert_eq!(
+            time_until(at(15, 30), &now),
+            Duration::from_secs(79 * 60 + 30)
+        );
+        // Already past today, so it's tomorrow's
+        assert_eq!(
+            time_until(at(9, 0), &now),
+            Duration::from_secs((18 * 60 + 49) * 60 + 30)
+        );
+        assert_eq!(
+            time_until(at(14, 10), &now),
+            Duration::from_secs(24 * 3600 - 30)
+        );
+
+        // The time of day is read in the local zone of `now`
+        let local = now.with_timezone(&FixedOffset::east_opt(2 * 3600).unwrap());
+        assert_eq!(
+            time_until(at(16, 30), &local),
+            Duration::from_secs(19 * 60 + 30)
+        );
+    }
+}
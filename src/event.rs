@@ -15,22 +15,16 @@ pub struct Events {
     streams: tokio_stream::StreamMap<&'static str, Stream>,
 }
 
-impl Default for Events {
-    fn default() -> Self {
+impl Events {
+    pub fn new(tick_rate: std::time::Duration) -> Self {
         Self {
             streams: tokio_stream::StreamMap::from_iter([
                 ("crossterm", crossterm_stream()),
                 ("render", render_stream()),
-                ("tick", tick_stream()),
+                ("tick", tick_stream(tick_rate)),
             ]),
         }
     }
-}
-
-impl Events {
-    pub fn new() -> Self {
-        Self::default()
-    }
 
     pub async fn next(&mut self) -> Option<Event> {
         self.streams.next().await.map(|(_name, event)| event)
@@ -61,11 +55,9 @@ fn render_stream() -> Stream {
     Box::pin(IntervalStream::new(render_interval).map(|_| Event::Render))
 }
 
-fn tick_stream() -> Stream {
+fn tick_stream(tick_delay: std::time::Duration) -> Stream {
     use tokio_stream::wrappers::IntervalStream;
 
-    const TICK_RATE: f64 = 1.0;
-    let tick_delay = std::time::Duration::from_secs_f64(1.0 / TICK_RATE);
     let tick_interval = tokio::time::interval(tick_delay);
     Box::pin(IntervalStream::new(tick_interval).map(|_| Event::Tick))
 }
@@ -0,0 +1,353 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::clock::{self, SharedClock};
+use crate::parser::{parse_duration, ParseTimeError};
+use crate::pomodoro::PomodoroState;
+use crate::session::{Session, SessionSnapshot, SnapshotMismatch};
+use crate::timer::{Timer, TimerSnapshot, TimerStatus};
+
+const DEFAULT_BREAK_FRACTION: u32 = 5;
+
+/// One row of a tiered break table as given on the command line, `up_to:length` or just
+/// `length` for any focus longer than the other tiers.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Tier {
+    pub up_to: Option<Duration>,
+    pub length: Duration,
+}
+
+impl FromStr for Tier {
+    type Err = ParseTimeError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.split_once(':') {
+            Some((up_to, length)) => Ok(Tier {
+                up_to: Some(parse_duration(up_to)?),
+                length: parse_duration(length).map_err(|error| error.offset(up_to.len() + 1))?,
+            }),
+            None => Ok(Tier {
+                up_to: None,
+                length: parse_duration(input)?,
+            }),
+        }
+    }
+}
+
+/// How long the break after a focus lasts, worked out from how long the focus ran.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BreakRule {
+    /// A break of the focus divided by this, e.g. 5 for a fifth
+    Fraction(u32),
+    /// The first tier the focus fits in; a focus longer than all of them gets the last one
+    Tiered(Vec<Tier>),
+}
+
+impl Default for BreakRule {
+    fn default() -> Self {
+        BreakRule::Fraction(DEFAULT_BREAK_FRACTION)
+    }
+}
+
+impl BreakRule {
+    pub fn break_for(&self, focus: Duration) -> Duration {
+        match self {
+            BreakRule::Fraction(0) => Duration::ZERO,
+            BreakRule::Fraction(fraction) => focus / *fraction,
+            BreakRule::Tiered(tiers) => tiers
+                .iter()
+                .find(|tier| tier.up_to.is_none_or(|up_to| focus <= up_to))
+                .or(tiers.last())
+                .map_or(Duration::ZERO, |tier| tier.length),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FlowtimeSnapshot {
+    pub state: PomodoroState,
+    pub rule: BreakRule,
+    pub total_sessions: usize,
+    pub completed: usize,
+    pub focused: Duration,
+    pub last_focus: Duration,
+    pub timer: TimerSnapshot,
+}
+
+/// Focus that counts up until ended, followed by a break in proportion to it. It moves through
+/// the same states as a `Pomodoro`, so the API, webhooks and checkpoints follow it alike.
+pub struct Flowtime {
+    state: PomodoroState,
+    rule: BreakRule,
+    /// Completes after this many focus sessions; 0 keeps cycling until stopped
+    total_sessions: usize,
+    completed: usize,
+    /// Time spent in focus sessions that were ended
+    focused: Duration,
+    last_focus: Duration,
+    timer: Timer,
+}
+
+impl Flowtime {
+    pub fn new(rule: BreakRule, total_sessions: usize, clock: SharedClock) -> Self {
+        Flowtime {
+            state: PomodoroState::Focus(1),
+            rule,
+            total_sessions,
+            completed: 0,
+            focused: Duration::ZERO,
+            last_focus: Duration::ZERO,
+            timer: focus_timer(clock),
+        }
+    }
+
+    pub fn snapshot(&self) -> FlowtimeSnapshot {
+        FlowtimeSnapshot {
+            state: self.state,
+            rule: self.rule.clone(),
+            total_sessions: self.total_sessions,
+            completed: self.completed,
+            focused: self.focused,
+            last_focus: self.last_focus,
+            timer: self.timer.snapshot(),
+        }
+    }
+
+    /// Rebuilds a flowtime from a snapshot. A break that ran out moves on to the next focus,
+    /// which starts from zero like any focus.
+    pub fn restore(snapshot: FlowtimeSnapshot, clock: SharedClock) -> Self {
+        let mut flowtime = Flowtime {
+            state: snapshot.state,
+            rule: snapshot.rule,
+            total_sessions: snapshot.total_sessions,
+            completed: snapshot.completed,
+            focused: snapshot.focused,
+            last_focus: snapshot.last_focus,
+            timer: Timer::restore(snapshot.timer, clock),
+        };
+
+        if !flowtime.is_focus()
+            && flowtime.timer.get_status() == TimerStatus::Running
+            && flowtime.timer.is_done()
+        {
+            flowtime.next_phase();
+        }
+        flowtime
+    }
+
+    pub fn tick(&mut self) {
+        if self.timer.get_status() == TimerStatus::Exit {
+            self.state = PomodoroState::Completed;
+        } else if matches!(self.state, PomodoroState::Break(_)) && self.timer.is_done() {
+            self.next_phase();
+        }
+    }
+
+    /// Ends the focus and takes the break it earned, or cuts the break short.
+    pub fn next_phase(&mut self) {
+        match self.state {
+            PomodoroState::Focus(session) => {
+                self.last_focus = self.timer.elapsed_time();
+                self.focused += self.last_focus;
+                self.completed += 1;
+
+                let length = self.rule.break_for(self.last_focus);
+                self.state = PomodoroState::Break(session);
+                self.timer = Timer::builder(length)
+                    .name("Break")
+                    .clock(self.timer.get_clock())
+                    .build();
+            }
+            PomodoroState::Break(session)
+                if self.total_sessions > 0 && session >= self.total_sessions =>
+            {
+                self.state = PomodoroState::Completed;
+            }
+            PomodoroState::Break(session) => {
+                self.state = PomodoroState::Focus(session + 1);
+                self.timer = focus_timer(self.timer.get_clock());
+            }
+            _ => {}
+        }
+    }
+
+    pub fn finish(&mut self) {
+        self.state = PomodoroState::Completed;
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.state == PomodoroState::Completed
+    }
+
+    pub fn is_focus(&self) -> bool {
+        matches!(self.state, PomodoroState::Focus(_))
+    }
+
+    pub fn get_state(&self) -> PomodoroState {
+        self.state
+    }
+
+    pub fn get_current_session(&self) -> usize {
+        self.state.session().unwrap_or(self.completed)
+    }
+
+    pub fn get_total_sessions(&self) -> usize {
+        self.total_sessions
+    }
+
+    pub fn get_completed(&self) -> usize {
+        self.completed
+    }
+
+    pub fn get_focused(&self) -> Duration {
+        self.focused
+    }
+
+    pub fn get_last_focus(&self) -> Duration {
+        self.last_focus
+    }
+
+    /// How long the running focus has gone on, zero during a break.
+    pub fn get_running_focus(&self) -> Duration {
+        match self.state {
+            PomodoroState::Focus(_) => self.timer.elapsed_time(),
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// The break ending the focus now would earn.
+    pub fn get_earned_break(&self) -> Duration {
+        match self.state {
+            PomodoroState::Focus(_) => self.rule.break_for(self.timer.elapsed_time()),
+            _ => Duration::ZERO,
+        }
+    }
+
+    pub fn get_timer(&mut self) -> &mut Timer {
+        &mut self.timer
+    }
+}
+
+// Focus runs on a `Timer` that never runs out, like the stopwatch
+fn focus_timer(clock: SharedClock) -> Timer {
+    Timer::with_clock(Duration::MAX, "Focus".to_string(), clock)
+}
+
+pub struct FlowtimeSession {
+    flowtime: Flowtime,
+}
+
+impl FlowtimeSession {
+    pub fn new(rule: BreakRule, total_sessions: usize) -> Self {
+        FlowtimeSession {
+            flowtime: Flowtime::new(rule, total_sessions, clock::system_clock()),
+        }
+    }
+
+    pub fn restore(snapshot: FlowtimeSnapshot) -> Self {
+        FlowtimeSession {
+            flowtime: Flowtime::restore(snapshot, clock::system_clock()),
+        }
+    }
+}
+
+impl Session for FlowtimeSession {
+    fn tick(&mut self) {
+        self.flowtime.tick()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.flowtime.is_completed()
+    }
+
+    fn toggle_pause(&mut self) {
+        self.flowtime.timer.toggle_pause();
+    }
+
+    fn get_timer(&mut self) -> Option<&mut Timer> {
+        Some(self.flowtime.get_timer())
+    }
+
+    fn get_flowtime(&mut self) -> Option<&mut Flowtime> {
+        Some(&mut self.flowtime)
+    }
+
+    fn snapshot(&self) -> SessionSnapshot {
+        SessionSnapshot::Flowtime(self.flowtime.snapshot())
+    }
+
+    fn restore(&mut self, snapshot: SessionSnapshot) -> Result<(), SnapshotMismatch> {
+        match snapshot {
+            SessionSnapshot::Flowtime(snapshot) => {
+                let clock = self.flowtime.timer.get_clock();
+                self.flowtime = Flowtime::restore(snapshot, clock);
+                Ok(())
+            }
+            _ => Err(SnapshotMismatch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use std::sync::Arc;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn test_break_rules() {
+        assert_eq!(BreakRule::default().break_for(40 * MINUTE), 8 * MINUTE);
+
+        let tiers = ["25m:5m", "50m:8m", "90m:10m", "15m"]
+            .map(|tier| tier.parse().unwrap())
+            .to_vec();
+        let rule = BreakRule::Tiered(tiers);
+        assert_eq!(rule.break_for(10 * MINUTE), 5 * MINUTE);
+        assert_eq!(rule.break_for(50 * MINUTE), 8 * MINUTE);
+        assert_eq!(rule.break_for(3 * 60 * MINUTE), 15 * MINUTE);
+
+        let error = "25m:5x".parse::<Tier>().unwrap_err();
+        assert_eq!(error.span(), 5..6);
+    }
+
+    #[test]
+    fn test_cycle() {
+        let clock = Arc::new(ManualClock::new());
+        let mut flowtime = Flowtime::new(BreakRule::default(), 2, clock.clone());
+
+        // Focus runs until ended, however long that is
+        clock.advance(50 * MINUTE);
+        flowtime.tick();
+        assert_eq!(flowtime.get_state(), PomodoroState::Focus(1));
+        assert_eq!(flowtime.get_earned_break(), 10 * MINUTE);
+        assert_eq!(flowtime.get_running_focus(), 50 * MINUTE);
+
+        flowtime.next_phase();
+        assert_eq!(flowtime.get_state(), PomodoroState::Break(1));
+        assert_eq!(flowtime.get_timer().get_duration(), 10 * MINUTE);
+
+        // A break that ran out while down is over by the time it's picked back up
+        let mut snapshot = flowtime.snapshot();
+        snapshot.timer.elapsed += 15 * MINUTE;
+        let mut restored = Flowtime::restore(snapshot, clock.clone());
+        assert_eq!(restored.get_state(), PomodoroState::Focus(2));
+        assert_eq!(restored.get_timer().elapsed_time(), Duration::ZERO);
+
+        clock.advance(10 * MINUTE);
+        flowtime.tick();
+        assert_eq!(flowtime.get_state(), PomodoroState::Focus(2));
+
+        clock.advance(20 * MINUTE);
+        restored = Flowtime::restore(flowtime.snapshot(), clock.clone());
+        restored.next_phase();
+        assert_eq!(restored.get_focused(), 70 * MINUTE);
+        assert_eq!(restored.get_completed(), 2);
+
+        // Cutting the last break short ends the target
+        restored.next_phase();
+        assert!(restored.is_completed());
+    }
+}
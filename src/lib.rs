@@ -0,0 +1,46 @@
+//! The timer engine, pomodoro state machine, duration parser and shared-session client behind
+//! the `pomoduro` binary.
+
+pub mod client;
+pub mod clock;
+pub mod flowtime;
+pub mod mob;
+pub mod parser;
+pub mod plan;
+pub mod pomodoro;
+pub mod session;
+pub mod standup;
+pub mod stopwatch;
+pub mod timer;
+pub mod vote;
+
+// Terminal app, servers and storage used by the binary, not part of the stable API
+#[doc(hidden)]
+pub mod api;
+#[doc(hidden)]
+pub mod app;
+#[doc(hidden)]
+pub mod checkpoint;
+#[doc(hidden)]
+pub mod event;
+#[doc(hidden)]
+pub mod metrics;
+#[doc(hidden)]
+pub mod paths;
+#[doc(hidden)]
+pub mod stats;
+#[doc(hidden)]
+pub mod tui;
+#[doc(hidden)]
+pub mod ui;
+#[doc(hidden)]
+pub mod webhook;
+#[doc(hidden)]
+pub mod websocket;
+
+pub use client::{ClientError, SessionClient, SessionClientBuilder, TimerMessage};
+pub use clock::{Clock, ManualClock, SharedClock, SystemClock};
+pub use parser::{parse_duration, parse_named_duration, parse_time_of_day, ParseTimeError};
+pub use pomodoro::{AutoStart, Pomodoro, PomodoroBuilder, PomodoroSnapshot, PomodoroState};
+pub use session::{Session, SessionSnapshot, SessionStatus, SnapshotMismatch};
+pub use timer::{Timer, TimerAction, TimerBuilder, TimerSnapshot, TimerStatus};
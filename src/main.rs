@@ -1,25 +1,34 @@
-mod app;
 mod cli;
-mod event;
-mod parser;
-mod pomodoro;
-mod timer;
-mod tui;
-mod ui;
-mod websocket;
 
-use crate::cli::Commands;
+use crate::cli::{Cli, Commands, RotationArgs, SharedModeArgs};
 
-use app::App;
+use chrono::Local;
+use pomoduro::api::ApiHandler;
+use pomoduro::app::App;
+use pomoduro::checkpoint::{Checkpoint, Checkpointer};
+use pomoduro::clock;
+use pomoduro::flowtime::BreakRule;
+use pomoduro::plan::Plan;
+use pomoduro::pomodoro::{AutoStart, Lengths, Pomodoro};
+use pomoduro::stats::{self, RoomReport, StatsRecorder};
+use pomoduro::tui;
+use pomoduro::vote::VoteRule;
+use pomoduro::webhook::{WebhookNotifier, WebhookUrl};
+use pomoduro::websocket::{self, Role};
 use std::fs::File;
-use std::io::Write;
+use std::io::{self, IsTerminal, Write};
 use std::time::Duration;
 use std::{error::Error, net::SocketAddr};
+use tokio::net::TcpListener;
 use tracing_subscriber::EnvFilter;
 
-const FOCUS_DURATION: u64 = 25;
-const BREAK_DURATION: u64 = 5;
-const LONG_BREAK_DURATION: u64 = 15;
+const FOCUS_DURATION: u64 = 25 * 60;
+const BREAK_DURATION: u64 = 5 * 60;
+const LONG_BREAK_DURATION: u64 = 15 * 60;
+const MOB_TURN_DURATION: u64 = 10 * 60;
+const MOB_BREAK_DURATION: u64 = 10 * 60;
+const MOB_BREAKS_EVERY: usize = 6;
+const STANDUP_TIMEBOX: u64 = 2 * 60;
 
 fn clear_log_file(path: &str) -> std::io::Result<()> {
     let mut file = File::create(path)?;
@@ -29,6 +38,163 @@ fn clear_log_file(path: &str) -> std::io::Result<()> {
     Ok(())
 }
 
+fn rotation_settings(rotation: &RotationArgs) -> (Duration, Duration, usize) {
+    (
+        rotation
+            .turn_duration
+            .unwrap_or(Duration::from_secs(MOB_TURN_DURATION)),
+        rotation
+            .break_duration
+            .unwrap_or(Duration::from_secs(MOB_BREAK_DURATION)),
+        rotation.breaks_every.unwrap_or(MOB_BREAKS_EVERY),
+    )
+}
+
+fn new_shared_app(
+    mode: &SharedModeArgs,
+    tick_rate: Duration,
+    role: Role,
+    vote_rule: VoteRule,
+) -> (App, websocket::WebSocketHandler) {
+    if !mode.standup.is_empty() {
+        return App::new_shared_standup(
+            mode.standup.clone(),
+            mode.timebox.unwrap_or(Duration::from_secs(STANDUP_TIMEBOX)),
+            tick_rate,
+            role,
+            vote_rule,
+        );
+    }
+
+    if mode.mob.is_empty() {
+        let pomodoro = Pomodoro::builder()
+            .sessions(4)
+            .focus(Duration::from_secs(FOCUS_DURATION))
+            .short_break(Duration::from_secs(BREAK_DURATION))
+            .long_break(Duration::from_secs(LONG_BREAK_DURATION))
+            .build();
+        return App::new_shared_pomodoro(pomodoro, tick_rate, role, vote_rule);
+    }
+
+    let (turn_duration, break_duration, breaks_every) = rotation_settings(&mode.rotation);
+    App::new_shared_mob(
+        mode.mob.clone(),
+        turn_duration,
+        break_duration,
+        breaks_every,
+        tick_rate,
+        role,
+        vote_rule,
+    )
+}
+
+/// Turns `--focus` or `--break` with their `-step` and `-max` flags into `Lengths`: a ramp from
+/// the one length given when there is a step, otherwise the length or list of lengths given.
+fn lengths(
+    given: &[Duration],
+    default: Duration,
+    step: Option<Duration>,
+    max: Option<Duration>,
+) -> Lengths {
+    match (given, step) {
+        (given, Some(step)) => Lengths::Ramp {
+            start: given.first().copied().unwrap_or(default),
+            step,
+            max,
+        },
+        ([], None) => default.into(),
+        ([length], None) => (*length).into(),
+        (given, None) => Lengths::List(given.to_vec()),
+    }
+}
+
+fn print_standup_summary(app: &mut App) {
+    if let Some(summary) = app.get_standup_summary() {
+        println!("{}", summary);
+    }
+}
+
+fn print_overrun(app: &mut App) {
+    if let Some(overrun) = app.get_overrun() {
+        let seconds = overrun.as_secs();
+        println!("Overran by {}m {}s", seconds / 60, seconds % 60);
+    }
+}
+
+fn print_focused(app: &mut App) {
+    if let Some(flowtime) = app.get_session_info().flowtime {
+        let minutes = (flowtime.focused + flowtime.running_focus).as_secs() / 60;
+        println!("Focused for {}h {}m", minutes / 60, minutes % 60);
+    }
+}
+
+async fn serve_api(app: &mut App, http: Option<SocketAddr>) -> Result<(), Box<dyn Error>> {
+    if let Some(addr) = http {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| format!("Could not serve the HTTP API on {}: {}", addr, e))?;
+        let api_handler = ApiHandler::new();
+        app.set_api_handler(api_handler.clone());
+        tokio::spawn(async move { api_handler.serve(listener).await });
+    }
+    Ok(())
+}
+
+fn notify_webhooks(app: &mut App, webhooks: &[WebhookUrl], task: Option<String>) {
+    if !webhooks.is_empty() {
+        app.set_webhooks(WebhookNotifier::new(webhooks.to_vec(), task));
+    }
+}
+
+/// Offers to pick up an interrupted session before starting a new one. Without a terminal to
+/// answer from, such as in a script, it starts the new one.
+fn offer_resume() -> io::Result<Option<Checkpoint>> {
+    if !io::stdin().is_terminal() {
+        return Ok(None);
+    }
+    let Some(checkpoint) = Checkpoint::load()? else {
+        return Ok(None);
+    };
+
+    print!("Found an unfinished {}. Resume it? [y/N] ", checkpoint);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    match answer.trim() {
+        "y" | "Y" | "yes" => Ok(Some(checkpoint)),
+        _ => Ok(None),
+    }
+}
+
+async fn resume(
+    checkpoint: Checkpoint,
+    cli: &Cli,
+    tick_rate: Duration,
+) -> Result<(), Box<dyn Error>> {
+    let task = checkpoint.task.clone();
+    let Some(mut app) = App::resume(checkpoint, tick_rate) else {
+        Checkpointer::new(None).clear();
+        println!("The interrupted session ran out while pomoduro wasn't running");
+        return Ok(());
+    };
+
+    serve_api(&mut app, cli.http).await?;
+    notify_webhooks(&mut app, &cli.webhooks, task.clone());
+    app.set_suspend_policy(cli.on_suspend);
+    app.enable_checkpoints(task);
+    app.run(&mut tui::init()?).await?;
+    tui::restore()?;
+
+    Ok(())
+}
+
+fn participant_name(name: &Option<String>) -> String {
+    name.clone()
+        .or_else(|| std::env::var("USER").ok())
+        .unwrap_or_else(|| "anonymous".to_string())
+}
+
 fn setup_tracing() {
     let file_appender = tracing_appender::rolling::never("./log", "pomoduro.log");
 
@@ -46,59 +212,196 @@ async fn main() -> Result<(), Box<dyn Error>> {
     setup_tracing();
 
     let cli = cli::parse();
-    let tick_rate = Duration::from_millis(250);
+    let tick_rate = Duration::from_secs(1);
 
     match &cli.command {
-        Some(Commands::Timer { duration, name }) => {
+        Some(Commands::Timer {
+            duration,
+            until,
+            name,
+            overtime,
+        }) => {
+            if let Some(checkpoint) = offer_resume()? {
+                return resume(checkpoint, &cli, tick_rate).await;
+            }
+
+            let duration = match until {
+                Some(until) => clock::time_until(*until, &Local::now()),
+                None => duration.unwrap_or_default(),
+            };
             let name = name.as_ref().unwrap_or(&String::from("Timer")).to_string();
-            App::new_timer(*duration, name, tick_rate)
-                .run(&mut tui::init()?)
-                .await?;
+            let mut app = App::new_timer(duration, name, tick_rate);
+            if let Some(timer) = app.get_timer() {
+                timer.set_overtime(*overtime);
+            }
+            serve_api(&mut app, cli.http).await?;
+            app.set_suspend_policy(cli.on_suspend);
+            app.enable_checkpoints(None);
+            app.run(&mut tui::init()?).await?;
             tui::restore()?;
+            print_overrun(&mut app);
         }
         Some(Commands::Pomodoro {
             sessions,
+            continuous,
+            until,
+            long_break_every,
             focus_duration,
+            focus_step,
+            focus_max,
             break_duration,
+            break_step,
+            break_max,
             long_break_duration,
+            task,
+            manual_focus,
+            manual_breaks,
+            overtime,
+        }) => {
+            if let Some(checkpoint) = offer_resume()? {
+                return resume(checkpoint, &cli, tick_rate).await;
+            }
+
+            let total_sessions = match continuous {
+                true => 0,
+                false => sessions.unwrap_or(4),
+            };
+            let mut builder = Pomodoro::builder()
+                .sessions(total_sessions)
+                .long_break_every(long_break_every.unwrap_or(4))
+                .focus(lengths(
+                    focus_duration,
+                    Duration::from_secs(FOCUS_DURATION),
+                    *focus_step,
+                    *focus_max,
+                ))
+                .short_break(lengths(
+                    break_duration,
+                    Duration::from_secs(BREAK_DURATION),
+                    *break_step,
+                    *break_max,
+                ))
+                .long_break(long_break_duration.unwrap_or(Duration::from_secs(LONG_BREAK_DURATION)))
+                .auto_start(AutoStart {
+                    focus: !manual_focus,
+                    breaks: !manual_breaks,
+                })
+                .overtime(*overtime);
+            if let Some(until) = until {
+                builder = builder.fit_within(clock::time_until(*until, &Local::now()));
+            }
+
+            let mut app = App::new_pomodoro(builder.build(), tick_rate);
+            serve_api(&mut app, cli.http).await?;
+            notify_webhooks(&mut app, &cli.webhooks, task.clone());
+            app.set_suspend_policy(cli.on_suspend);
+            app.enable_checkpoints(task.clone());
+            app.run(&mut tui::init()?).await?;
+            tui::restore()?;
+            print_overrun(&mut app);
+        }
+        Some(Commands::Flowtime {
+            sessions,
+            break_fraction,
+            tiers,
+            task,
         }) => {
-            let total_sessions = sessions.unwrap_or(4);
-            let focus_duration = focus_duration.unwrap_or(Duration::from_secs(FOCUS_DURATION));
-            let break_duration = break_duration.unwrap_or(Duration::from_secs(BREAK_DURATION));
-            let long_break_duration =
-                long_break_duration.unwrap_or(Duration::from_secs(LONG_BREAK_DURATION));
-
-            App::new_pomodoro(
-                total_sessions,
-                focus_duration,
+            if let Some(checkpoint) = offer_resume()? {
+                return resume(checkpoint, &cli, tick_rate).await;
+            }
+
+            let rule = match tiers.is_empty() {
+                true => BreakRule::Fraction(*break_fraction),
+                false => BreakRule::Tiered(tiers.clone()),
+            };
+
+            let mut app = App::new_flowtime(rule, *sessions, tick_rate);
+            serve_api(&mut app, cli.http).await?;
+            notify_webhooks(&mut app, &cli.webhooks, task.clone());
+            app.set_suspend_policy(cli.on_suspend);
+            app.enable_checkpoints(task.clone());
+            app.run(&mut tui::init()?).await?;
+            tui::restore()?;
+            print_focused(&mut app);
+        }
+        Some(Commands::Stopwatch { export }) => {
+            let mut app = App::new_stopwatch(tick_rate);
+            serve_api(&mut app, cli.http).await?;
+            app.set_suspend_policy(cli.on_suspend);
+            app.run(&mut tui::init()?).await?;
+            tui::restore()?;
+
+            if let Some(path) = export {
+                app.export_laps(path)?;
+                println!("Laps written to {}", path.display());
+            }
+        }
+        Some(Commands::Mob { drivers, rotation }) => {
+            let (turn_duration, break_duration, breaks_every) = rotation_settings(rotation);
+
+            let mut app = App::new_mob(
+                drivers.clone(),
+                turn_duration,
                 break_duration,
-                long_break_duration,
+                breaks_every,
                 tick_rate,
-            )
-            .run(&mut tui::init()?)
-            .await?;
+            );
+            serve_api(&mut app, cli.http).await?;
+            app.set_suspend_policy(cli.on_suspend);
+            app.run(&mut tui::init()?).await?;
+            tui::restore()?;
+        }
+        Some(Commands::Standup { speakers, timebox }) => {
+            let timebox = timebox.unwrap_or(Duration::from_secs(STANDUP_TIMEBOX));
+
+            let mut app = App::new_standup(speakers.clone(), timebox, tick_rate);
+            serve_api(&mut app, cli.http).await?;
+            app.set_suspend_policy(cli.on_suspend);
+            app.run(&mut tui::init()?).await?;
             tui::restore()?;
+
+            print_standup_summary(&mut app);
         }
-        Some(Commands::Host { port }) => {
+        Some(Commands::Run { plan }) => {
+            let mut app = App::new_plan(Plan::load(plan)?, tick_rate);
+            serve_api(&mut app, cli.http).await?;
+            app.set_suspend_policy(cli.on_suspend);
+            app.run(&mut tui::init()?).await?;
+            tui::restore()?;
+        }
+        Some(Commands::Host {
+            port,
+            vote_rule,
+            room,
+            name,
+            browser_control,
+            mode,
+        }) => {
             clear_log_file("./log/pomoduro.log")?;
 
             let port = port.unwrap_or(8080);
             let addr = SocketAddr::from(([127, 0, 0, 1], port));
 
-            let (mut app, ws_handler) = App::new_shared_pomodoro(
-                4,
-                Duration::from_secs(FOCUS_DURATION),
-                Duration::from_secs(BREAK_DURATION),
-                Duration::from_secs(LONG_BREAK_DURATION),
-                tick_rate,
-            );
+            let (mut app, ws_handler) = new_shared_app(mode, tick_rate, Role::Host, *vote_rule);
+
+            app.set_stats_recorder(StatsRecorder::new(room));
+            serve_api(&mut app, cli.http).await?;
+            notify_webhooks(&mut app, &cli.webhooks, None);
 
-            tokio::spawn(async move { ws_handler.host(&addr).await });
+            let name = participant_name(name);
+            let browser_control = *browser_control;
+            tokio::spawn(async move { ws_handler.host(&addr, name, browser_control).await });
             app.run(&mut tui::init()?).await?;
 
             tui::restore()?;
+            print_standup_summary(&mut app);
         }
-        Some(Commands::Join { address, port }) => {
+        Some(Commands::Join {
+            address,
+            port,
+            name,
+            mode,
+        }) => {
             use tokio::net;
 
             let port = port.unwrap_or(8080);
@@ -111,19 +414,29 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
             let addr = addr.parse::<SocketAddr>().unwrap();
 
-            let (mut app, ws_handler) = App::new_shared_pomodoro(
-                4,
-                Duration::from_secs(FOCUS_DURATION),
-                Duration::from_secs(BREAK_DURATION),
-                Duration::from_secs(LONG_BREAK_DURATION),
-                tick_rate,
-            );
+            let (mut app, ws_handler) =
+                new_shared_app(mode, tick_rate, Role::Guest, VoteRule::default());
 
-            tokio::spawn(async move { ws_handler.join(&addr).await });
+            let name = participant_name(name);
+            tokio::spawn(async move { ws_handler.join(&addr, name).await });
+            serve_api(&mut app, cli.http).await?;
+            notify_webhooks(&mut app, &cli.webhooks, None);
 
             app.run(&mut tui::init()?).await?;
             tui::restore()?;
+            print_standup_summary(&mut app);
         }
+        Some(Commands::Resume) => match Checkpoint::load()? {
+            Some(checkpoint) => resume(checkpoint, &cli, tick_rate).await?,
+            None => println!("No interrupted session to resume"),
+        },
+        Some(Commands::Stats { room }) => match stats::load_records(room) {
+            Ok(records) => println!("{}", RoomReport::new(room, &records)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                println!("No statistics recorded for room `{}`", room)
+            }
+            Err(e) => return Err(e.into()),
+        },
         _ => (),
     };
 
@@ -0,0 +1,175 @@
+use crate::pomodoro::PomodoroState;
+use crate::session::SessionStatus;
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+/// Process-wide counters, updated where things happen and rendered on `GET /metrics`.
+pub static METRICS: Metrics = Metrics::new();
+
+const PHASES: [&str; 5] = ["ready", "focus", "break", "long_break", "completed"];
+const NO_PHASE: u8 = u8::MAX;
+
+pub struct Metrics {
+    peers: AtomicU64,
+    rooms: AtomicU64,
+    messages_relayed: AtomicU64,
+    handshake_failures: AtomicU64,
+    pomodoros_completed: AtomicU64,
+    seconds_remaining: AtomicU64,
+    phase: AtomicU8,
+}
+
+impl Metrics {
+    pub const fn new() -> Self {
+        Metrics {
+            peers: AtomicU64::new(0),
+            rooms: AtomicU64::new(0),
+            messages_relayed: AtomicU64::new(0),
+            handshake_failures: AtomicU64::new(0),
+            pomodoros_completed: AtomicU64::new(0),
+            seconds_remaining: AtomicU64::new(0),
+            phase: AtomicU8::new(NO_PHASE),
+        }
+    }
+
+    pub fn peer_connected(&self) {
+        self.peers.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn peer_disconnected(&self) {
+        self.peers.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn room_opened(&self) {
+        self.rooms.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn message_relayed(&self) {
+        self.messages_relayed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn handshake_failed(&self) {
+        self.handshake_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn pomodoros_completed(&self, count: u64) {
+        self.pomodoros_completed.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn observe(&self, status: &SessionStatus) {
+        let phase = match status.phase {
+            Some(PomodoroState::Ready) => 0,
+            Some(PomodoroState::Focus(_)) => 1,
+            Some(PomodoroState::Break(_)) => 2,
+            Some(PomodoroState::LongBreak(_)) => 3,
+            Some(PomodoroState::Completed) => 4,
+            None => NO_PHASE,
+        };
+
+        self.phase.store(phase, Ordering::Relaxed);
+        self.seconds_remaining
+            .store(status.remaining_secs, Ordering::Relaxed);
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        let mut metric = |name: &str, kind: &str, help: &str, value: &AtomicU64| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            let _ = writeln!(out, "{} {}", name, value.load(Ordering::Relaxed));
+        };
+
+        metric(
+            "pomoduro_connected_peers",
+            "gauge",
+            "Peers currently connected to the host",
+            &self.peers,
+        );
+        metric(
+            "pomoduro_rooms",
+            "gauge",
+            "Rooms currently hosted",
+            &self.rooms,
+        );
+        metric(
+            "pomoduro_messages_relayed_total",
+            "counter",
+            "Messages relayed to peers",
+            &self.messages_relayed,
+        );
+        metric(
+            "pomoduro_handshake_failures_total",
+            "counter",
+            "Websocket handshakes that failed",
+            &self.handshake_failures,
+        );
+        metric(
+            "pomoduro_pomodoros_completed_total",
+            "counter",
+            "Focus sessions that ran to their break",
+            &self.pomodoros_completed,
+        );
+        metric(
+            "pomoduro_seconds_remaining",
+            "gauge",
+            "Seconds left in the current phase",
+            &self.seconds_remaining,
+        );
+
+        let current = self.phase.load(Ordering::Relaxed);
+        let _ = writeln!(out, "# HELP pomoduro_phase Current pomodoro phase");
+        let _ = writeln!(out, "# TYPE pomoduro_phase gauge");
+        for (index, phase) in PHASES.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "pomoduro_phase{{phase=\"{}\"}} {}",
+                phase,
+                u8::from(index as u8 == current)
+            );
+        }
+
+        out
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::new();
+        metrics.peer_connected();
+        metrics.peer_connected();
+        metrics.peer_disconnected();
+        metrics.message_relayed();
+        metrics.pomodoros_completed(1);
+        metrics.observe(&SessionStatus {
+            remaining_secs: 42,
+            duration_secs: 300,
+            paused: false,
+            phase: Some(PomodoroState::Break(1)),
+            current_session: 1,
+            total_sessions: 4,
+        });
+
+        let rendered = metrics.render();
+        assert!(rendered.contains("pomoduro_connected_peers 1\n"));
+        assert!(rendered.contains("pomoduro_messages_relayed_total 1\n"));
+        assert!(rendered.contains("pomoduro_handshake_failures_total 0\n"));
+        assert!(rendered.contains("pomoduro_pomodoros_completed_total 1\n"));
+        assert!(rendered.contains("pomoduro_seconds_remaining 42\n"));
+        assert!(rendered.contains("pomoduro_phase{phase=\"break\"} 1\n"));
+        assert!(rendered.contains("pomoduro_phase{phase=\"focus\"} 0\n"));
+        assert!(rendered.contains("# TYPE pomoduro_rooms gauge\n"));
+    }
+}
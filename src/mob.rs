@@ -0,0 +1,308 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::clock::{self, SharedClock};
+use crate::session::{Session, SessionSnapshot, SnapshotMismatch};
+use crate::timer::{Timer, TimerSnapshot, TimerStatus};
+
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum MobState {
+    Turn(usize),
+    Break(usize),
+    Completed,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MobSnapshot {
+    pub state: MobState,
+    pub drivers: Vec<String>,
+    pub turn_duration: Duration,
+    pub break_duration: Duration,
+    pub breaks_every: usize,
+    pub timer: Option<TimerSnapshot>,
+}
+
+#[derive(Clone)]
+pub struct Mob {
+    state: MobState,
+    drivers: Vec<String>,
+    turn_duration: Duration,
+    break_duration: Duration,
+    breaks_every: usize,
+    timer: Option<Timer>,
+    clock: SharedClock,
+}
+
+impl Mob {
+    pub fn new(
+        drivers: Vec<String>,
+        turn_duration: Duration,
+        break_duration: Duration,
+        breaks_every: usize,
+        clock: SharedClock,
+    ) -> Self {
+        let mut mob = Mob {
+            state: MobState::Turn(1),
+            drivers,
+            turn_duration,
+            break_duration,
+            breaks_every,
+            timer: None,
+            clock,
+        };
+        mob.timer = Some(mob.turn_timer(1));
+        mob
+    }
+
+    pub fn snapshot(&self) -> MobSnapshot {
+        MobSnapshot {
+            state: self.state,
+            drivers: self.drivers.clone(),
+            turn_duration: self.turn_duration,
+            break_duration: self.break_duration,
+            breaks_every: self.breaks_every,
+            timer: self.timer.as_ref().map(Timer::snapshot),
+        }
+    }
+
+    pub fn restore(snapshot: MobSnapshot, clock: SharedClock) -> Self {
+        Mob {
+            state: snapshot.state,
+            drivers: snapshot.drivers,
+            turn_duration: snapshot.turn_duration,
+            break_duration: snapshot.break_duration,
+            breaks_every: snapshot.breaks_every,
+            timer: snapshot
+                .timer
+                .map(|timer| Timer::restore(timer, clock.clone())),
+            clock,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        if let Some(timer) = &mut self.timer {
+            if timer.get_status() == TimerStatus::Exit {
+                self.state = MobState::Completed;
+            } else if timer.is_done() {
+                self.timer = self.next_timer();
+            }
+        }
+    }
+
+    fn next_timer(&mut self) -> Option<Timer> {
+        match self.state {
+            MobState::Turn(rotation)
+                if self.breaks_every > 0 && rotation % self.breaks_every == 0 =>
+            {
+                self.state = MobState::Break(rotation);
+                Some(Timer::with_clock(
+                    self.break_duration,
+                    "Break".to_string(),
+                    self.clock.clone(),
+                ))
+            }
+            MobState::Turn(rotation) | MobState::Break(rotation) => {
+                self.state = MobState::Turn(rotation + 1);
+                Some(self.turn_timer(rotation + 1))
+            }
+            MobState::Completed => None,
+        }
+    }
+
+    pub fn skip_turn(&mut self) {
+        self.timer = self.next_timer();
+    }
+
+    fn turn_timer(&self, rotation: usize) -> Timer {
+        Timer::with_clock(
+            self.turn_duration,
+            format!("{} driving", self.driver_for(rotation)),
+            self.clock.clone(),
+        )
+    }
+
+    fn driver_for(&self, rotation: usize) -> &str {
+        if self.drivers.is_empty() {
+            return "Nobody";
+        }
+        &self.drivers[(rotation - 1) % self.drivers.len()]
+    }
+
+    /// The current driver, or `None` while the mob is on a break.
+    pub fn get_driver(&self) -> Option<&str> {
+        match self.state {
+            MobState::Turn(rotation) => Some(self.driver_for(rotation)),
+            _ => None,
+        }
+    }
+
+    pub fn get_next_driver(&self) -> Option<&str> {
+        match self.state {
+            MobState::Turn(rotation) | MobState::Break(rotation) => {
+                Some(self.driver_for(rotation + 1))
+            }
+            MobState::Completed => None,
+        }
+    }
+
+    pub fn get_rotation(&self) -> usize {
+        match self.state {
+            MobState::Turn(rotation) | MobState::Break(rotation) => rotation,
+            MobState::Completed => 0,
+        }
+    }
+
+    pub fn get_timer(&mut self) -> Option<&mut Timer> {
+        self.timer.as_mut()
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.state == MobState::Completed
+    }
+
+    pub fn set_state(&mut self, state: MobState) {
+        self.state = state;
+    }
+}
+
+pub struct MobSession {
+    mob: Mob,
+}
+
+impl MobSession {
+    pub fn new(
+        drivers: Vec<String>,
+        turn_duration: Duration,
+        break_duration: Duration,
+        breaks_every: usize,
+    ) -> Self {
+        MobSession {
+            mob: Mob::new(
+                drivers,
+                turn_duration,
+                break_duration,
+                breaks_every,
+                clock::system_clock(),
+            ),
+        }
+    }
+
+    pub fn restore(snapshot: MobSnapshot) -> Self {
+        MobSession {
+            mob: Mob::restore(snapshot, clock::system_clock()),
+        }
+    }
+}
+
+impl Session for MobSession {
+    fn tick(&mut self) {
+        self.mob.tick()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.mob.is_completed()
+    }
+
+    fn toggle_pause(&mut self) {
+        if let Some(timer) = &mut self.mob.timer {
+            timer.toggle_pause();
+        }
+    }
+
+    fn get_timer(&mut self) -> Option<&mut Timer> {
+        self.mob.get_timer()
+    }
+
+    fn get_mob(&mut self) -> Option<&mut Mob> {
+        Some(&mut self.mob)
+    }
+
+    fn snapshot(&self) -> SessionSnapshot {
+        SessionSnapshot::Mob(self.mob.snapshot())
+    }
+
+    fn restore(&mut self, snapshot: SessionSnapshot) -> Result<(), SnapshotMismatch> {
+        match snapshot {
+            SessionSnapshot::Mob(snapshot) => {
+                let clock = self.mob.clock.clone();
+                self.mob = Mob::restore(snapshot, clock);
+                Ok(())
+            }
+            _ => Err(SnapshotMismatch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use std::sync::Arc;
+
+    const TURN: Duration = Duration::from_secs(10 * 60);
+
+    fn mob(breaks_every: usize) -> (Mob, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock::new());
+        let mob = Mob::new(
+            vec!["Alice".to_string(), "Bob".to_string(), "Carol".to_string()],
+            TURN,
+            TURN,
+            breaks_every,
+            clock.clone(),
+        );
+        (mob, clock)
+    }
+
+    fn run_turn(mob: &mut Mob, clock: &ManualClock) {
+        clock.advance(TURN);
+        mob.tick();
+    }
+
+    #[test]
+    fn test_rotation() {
+        let (mut mob, clock) = mob(0);
+        assert_eq!(mob.get_driver(), Some("Alice"));
+        assert_eq!(mob.get_next_driver(), Some("Bob"));
+
+        clock.advance(TURN / 2);
+        mob.tick();
+        assert_eq!(mob.get_driver(), Some("Alice"));
+
+        clock.advance(TURN / 2);
+        mob.tick();
+        assert_eq!(mob.get_driver(), Some("Bob"));
+
+        run_turn(&mut mob, &clock);
+        run_turn(&mut mob, &clock);
+        assert_eq!(mob.state, MobState::Turn(4));
+        assert_eq!(mob.get_driver(), Some("Alice"));
+        assert_eq!(mob.get_timer().unwrap().get_name(), "Alice driving");
+    }
+
+    #[test]
+    fn test_break_every_n_rotations() {
+        let (mut mob, clock) = mob(2);
+
+        run_turn(&mut mob, &clock);
+        assert_eq!(mob.state, MobState::Turn(2));
+
+        run_turn(&mut mob, &clock);
+        assert_eq!(mob.state, MobState::Break(2));
+        assert_eq!(mob.get_driver(), None);
+        assert_eq!(mob.get_next_driver(), Some("Carol"));
+
+        // A restored mob keeps to the clock it's given
+        let mut mob = Mob::restore(mob.snapshot(), clock.clone());
+        run_turn(&mut mob, &clock);
+        assert_eq!(mob.state, MobState::Turn(3));
+        assert_eq!(mob.get_driver(), Some("Carol"));
+    }
+
+    #[test]
+    fn test_quit() {
+        let (mut mob, _) = mob(2);
+        mob.get_timer().unwrap().set_status(TimerStatus::Exit);
+        mob.tick();
+        assert!(mob.is_completed());
+    }
+}
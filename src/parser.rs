@@ -1,63 +1,609 @@
-use std::{num::ParseIntError, time::Duration};
+use chrono::NaiveTime;
+use std::fmt;
+use std::num::{IntErrorKind, ParseIntError};
+use std::ops::Range;
+use std::time::Duration;
 use thiserror::Error;
 
+/// Every variant carries the byte span of the offending part of the input.
 #[derive(Error, Debug, PartialEq)]
 pub enum ParseTimeError {
     #[error("Time must be an integer")]
-    IntError(#[from] ParseIntError),
+    IntError {
+        source: ParseIntError,
+        span: Range<usize>,
+    },
 
-    #[error("Invalid unit format: `{0}`, Unit must be one of 'h', 'm', or 's'")]
-    InvalidUnit(String),
+    #[error(
+        "Invalid unit format: `{unit}`, Unit must be one of h, hr(s), hour(s), m, min(s), \
+         minute(s), s, sec(s) or second(s)"
+    )]
+    InvalidUnit { unit: String, span: Range<usize> },
 
-    #[error("Invalid character in time format: {0}")]
-    InvalidChar(String),
+    #[error("Invalid character in time format: {text}")]
+    InvalidChar { text: String, span: Range<usize> },
+
+    #[error("Invalid time of day: `{text}`, expected HH:MM")]
+    InvalidTimeOfDay { text: String, span: Range<usize> },
+
+    #[error("No duration given")]
+    Empty { span: Range<usize> },
+
+    /// `missing` is the unit a bare number most likely lacks, if there is one.
+    #[error("Ambiguous duration: `{text}`, give each number its own unit once")]
+    Ambiguous {
+        text: String,
+        span: Range<usize>,
+        missing: Option<&'static str>,
+    },
+
+    #[error("Duration out of range: `{text}`")]
+    OutOfRange { text: String, span: Range<usize> },
+}
+
+impl ParseTimeError {
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            ParseTimeError::IntError { span, .. }
+            | ParseTimeError::InvalidUnit { span, .. }
+            | ParseTimeError::InvalidChar { span, .. }
+            | ParseTimeError::InvalidTimeOfDay { span, .. }
+            | ParseTimeError::Empty { span }
+            | ParseTimeError::Ambiguous { span, .. }
+            | ParseTimeError::OutOfRange { span, .. } => span.clone(),
+        }
+    }
+
+    /// Moves the span for input that was parsed out of a longer string, `by` bytes in.
+    pub fn offset(mut self, by: usize) -> Self {
+        match &mut self {
+            ParseTimeError::IntError { span, .. }
+            | ParseTimeError::InvalidUnit { span, .. }
+            | ParseTimeError::InvalidChar { span, .. }
+            | ParseTimeError::InvalidTimeOfDay { span, .. }
+            | ParseTimeError::Empty { span }
+            | ParseTimeError::Ambiguous { span, .. }
+            | ParseTimeError::OutOfRange { span, .. } => {
+                *span = span.start + by..span.end + by;
+            }
+        }
+        self
+    }
+
+    /// `input` as it was probably meant, e.g. `25m` for `25mm`.
+    pub fn suggestion(&self, input: &str) -> Option<String> {
+        let span = self.span();
+        let replacement = match self {
+            ParseTimeError::InvalidUnit { unit, .. } => closest_unit(unit)?.to_string(),
+            ParseTimeError::Ambiguous {
+                text,
+                missing: Some(missing),
+                ..
+            } => format!("{}{}", text, missing),
+            _ => return None,
+        };
+        let (before, after) = (input.get(..span.start)?, input.get(span.end..)?);
+        Some(format!("{}{}{}", before, replacement, after))
+    }
+}
+
+/// A parse error shown against its input, with a caret under the offending part and a
+/// suggestion when there is one.
+#[derive(Debug)]
+pub struct Diagnostic {
+    input: String,
+    error: ParseTimeError,
+}
+
+impl Diagnostic {
+    pub fn new(input: &str, error: ParseTimeError) -> Self {
+        Diagnostic {
+            input: input.to_string(),
+            error,
+        }
+    }
+
+    pub fn error(&self) -> &ParseTimeError {
+        &self.error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let span = self.error.span();
+        let width = |text: Option<&str>| text.map_or(0, |text| text.chars().count());
+        let indent = width(self.input.get(..span.start));
+        let carets = width(self.input.get(span)).max(1);
+
+        write!(
+            f,
+            "{}\n\n    {}\n    {}{}",
+            self.error,
+            self.input,
+            " ".repeat(indent),
+            "^".repeat(carets)
+        )?;
+        if let Some(suggestion) = self.error.suggestion(&self.input) {
+            write!(f, " did you mean `{}`?", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostic {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Parses a 24-hour time of day such as `15:30` or `9:05`.
+pub fn parse_time_of_day(input: &str) -> Result<NaiveTime, ParseTimeError> {
+    let trimmed = Token::new(input).trim();
+    NaiveTime::parse_from_str(trimmed.as_str(), "%H:%M").map_err(|_| {
+        ParseTimeError::InvalidTimeOfDay {
+            text: trimmed.as_str().to_string(),
+            span: trimmed.span(),
+        }
+    })
+}
+
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+const SECONDS_PER_MINUTE: u64 = 60;
+const SECONDS_PER_HOUR: u64 = 60 * SECONDS_PER_MINUTE;
+const SECONDS_PER_DAY: u64 = 24 * SECONDS_PER_HOUR;
+const SECONDS_PER_WEEK: u64 = 7 * SECONDS_PER_DAY;
+
+const UNITS: [(&str, u64); 15] = [
+    ("h", SECONDS_PER_HOUR),
+    ("hr", SECONDS_PER_HOUR),
+    ("hrs", SECONDS_PER_HOUR),
+    ("hour", SECONDS_PER_HOUR),
+    ("hours", SECONDS_PER_HOUR),
+    ("m", SECONDS_PER_MINUTE),
+    ("min", SECONDS_PER_MINUTE),
+    ("mins", SECONDS_PER_MINUTE),
+    ("minute", SECONDS_PER_MINUTE),
+    ("minutes", SECONDS_PER_MINUTE),
+    ("s", 1),
+    ("sec", 1),
+    ("secs", 1),
+    ("second", 1),
+    ("seconds", 1),
+];
+
+/// A piece of the input along with the byte range it covers, so errors can point at it.
+#[derive(Clone, Copy, Debug)]
+struct Token<'a> {
+    input: &'a str,
+    start: usize,
+    end: usize,
+}
+
+impl<'a> Token<'a> {
+    fn new(input: &'a str) -> Self {
+        Token {
+            input,
+            start: 0,
+            end: input.len(),
+        }
+    }
+
+    fn as_str(&self) -> &'a str {
+        &self.input[self.start..self.end]
+    }
+
+    fn span(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The part of this token at `range`, counted from its own start.
+    fn slice(&self, range: Range<usize>) -> Token<'a> {
+        Token {
+            input: self.input,
+            start: self.start + range.start,
+            end: self.start + range.end,
+        }
+    }
+
+    fn split_at(&self, mid: usize) -> (Token<'a>, Token<'a>) {
+        (self.slice(0..mid), self.slice(mid..self.len()))
+    }
+
+    /// The parts between each `separator`.
+    fn split(&self, separator: char) -> Vec<Token<'a>> {
+        let mut parts = Vec::new();
+        let mut start = 0;
+        for (at, _) in self.as_str().match_indices(separator) {
+            parts.push(self.slice(start..at));
+            start = at + separator.len_utf8();
+        }
+        parts.push(self.slice(start..self.len()));
+        parts
+    }
+
+    fn trim_start(&self) -> Token<'a> {
+        let trimmed = self.as_str().trim_start();
+        self.slice(self.len() - trimmed.len()..self.len())
+    }
+
+    fn trim(&self) -> Token<'a> {
+        let trimmed = self.trim_start();
+        trimmed.slice(0..trimmed.as_str().trim_end().len())
+    }
+
+    /// The input from the start of this token to the end of `last`.
+    fn join(&self, last: Token<'a>) -> Token<'a> {
+        Token {
+            input: self.input,
+            start: self.start,
+            end: last.end,
+        }
+    }
+
+    fn invalid_char(&self) -> ParseTimeError {
+        ParseTimeError::InvalidChar {
+            text: self.as_str().to_string(),
+            span: self.span(),
+        }
+    }
+
+    fn invalid_unit(&self) -> ParseTimeError {
+        ParseTimeError::InvalidUnit {
+            unit: self.as_str().to_string(),
+            span: self.span(),
+        }
+    }
+
+    fn ambiguous(&self, missing: Option<&'static str>) -> ParseTimeError {
+        ParseTimeError::Ambiguous {
+            text: self.as_str().to_string(),
+            span: self.span(),
+            missing,
+        }
+    }
+
+    fn out_of_range(&self) -> ParseTimeError {
+        ParseTimeError::OutOfRange {
+            text: self.as_str().to_string(),
+            span: self.span(),
+        }
+    }
 }
 
+/// Parses a duration in any of the forms people type:
+///
+/// - units, in any order and optionally spaced: `25m`, `1h30m`, `1h 30m`, `30sec`, `2 minutes`
+/// - decimals: `1.5h`, `0.5m`
+/// - a plain number of minutes: `90`
+/// - a clock reading: `25:00` (minutes and seconds) or `1:30:00`
+/// - ISO-8601: `PT25M`, `PT1H30M`, `P1D`
 pub fn parse_duration(input: &str) -> Result<Duration, ParseTimeError> {
-    let mut total_duration = Duration::ZERO;
+    let whole = Token::new(input);
+    let input = whole.trim();
+    let text = input.as_str();
 
-    do_parse(input.trim(), 'h')
-        .and_then(|(duration, remaining)| {
-            total_duration += duration;
-            do_parse(remaining, 'm')
-        })
-        .and_then(|(duration, remaining)| {
-            total_duration += duration;
-            do_parse(remaining, 's')
+    if input.is_empty() {
+        Err(ParseTimeError::Empty { span: whole.span() })
+    } else if text.starts_with(['P', 'p']) {
+        parse_iso8601(input)
+    } else if text.contains(':') {
+        parse_clock(input)
+    } else if text.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+        to_duration(
+            scaled(parse_number(input)?, SECONDS_PER_MINUTE, input)?,
+            input,
+        )
+    } else {
+        parse_units(input)
+    }
+}
+
+/// Splits input such as `tea 4m` or `build 1h 30m` into the name in front and the duration
+/// at the end. The name is empty if there is none.
+pub fn parse_named_duration(input: &str) -> Result<(&str, Duration), ParseTimeError> {
+    let input = input.trim_end();
+    // Every place a word starts, the longest duration first
+    let starts = input
+        .char_indices()
+        .filter(|&(at, c)| {
+            !c.is_whitespace() && (at == 0 || input[..at].ends_with(char::is_whitespace))
         })
-        .and_then(|(duration, remaining)| {
-            total_duration += duration;
-            if remaining.is_empty() {
-                Ok(total_duration)
-            } else {
-                Err(ParseTimeError::InvalidUnit(remaining.to_string()))
-            }
+        .map(|(at, _)| at);
+
+    let mut last_error = None;
+    for start in starts {
+        match parse_duration(&input[start..]) {
+            Ok(duration) => return Ok((input[..start].trim(), duration)),
+            Err(error) => last_error = Some(error.offset(start)),
+        }
+    }
+    Err(last_error.unwrap_or(ParseTimeError::Empty {
+        span: 0..input.len(),
+    }))
+}
+
+/// A decimal number exactly as written: its digits without the point, `scale` of them after it.
+struct Number {
+    digits: u128,
+    scale: u32,
+}
+
+fn parse_number(text: Token) -> Result<Number, ParseTimeError> {
+    let (whole, fraction) = match text.as_str().split_once('.') {
+        Some((whole, fraction)) => (whole, fraction),
+        None => (text.as_str(), ""),
+    };
+    let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if whole.is_empty() || !is_digits(whole) || !is_digits(fraction) || text.as_str().ends_with('.')
+    {
+        return Err(text.invalid_char());
+    }
+    // Nanoseconds are as fine as a `Duration` goes
+    if fraction.len() > 9 {
+        return Err(text.out_of_range());
+    }
+
+    let digits = whole
+        .bytes()
+        .chain(fraction.bytes())
+        .try_fold(0u128, |digits, b| {
+            digits.checked_mul(10)?.checked_add(u128::from(b - b'0'))
         })
+        .ok_or_else(|| text.out_of_range())?;
+
+    Ok(Number {
+        digits,
+        scale: fraction.len() as u32,
+    })
 }
 
-fn do_parse(input: &str, unit: char) -> Result<(Duration, &str), ParseTimeError> {
-    match input.split_once(unit) {
-        Some((time, input)) => match time.parse::<u64>() {
-            Ok(time) => {
-                let duration = match unit {
-                    'h' => Duration::from_secs(time * 3600),
-                    'm' => Duration::from_secs(time * 60),
-                    's' => Duration::from_secs(time),
-                    _ => unreachable!(),
-                };
+/// `number` of `unit_seconds`, in nanoseconds.
+fn scaled(number: Number, unit_seconds: u64, token: Token) -> Result<u128, ParseTimeError> {
+    number
+        .digits
+        .checked_mul(u128::from(unit_seconds) * NANOS_PER_SECOND)
+        .map(|nanos| nanos / 10u128.pow(number.scale))
+        .ok_or_else(|| token.out_of_range())
+}
 
-                Ok((duration, input))
-            }
-            Err(e) => {
-                if time.chars().any(|c| c.is_alphabetic()) {
-                    Err(ParseTimeError::InvalidChar(time.to_string()))
-                } else {
-                    Err(ParseTimeError::IntError(e))
-                }
-            }
-        },
-        None => Ok((Duration::ZERO, input)),
+fn to_duration(nanos: u128, input: Token) -> Result<Duration, ParseTimeError> {
+    let seconds = u64::try_from(nanos / NANOS_PER_SECOND).map_err(|_| input.out_of_range())?;
+    Ok(Duration::new(seconds, (nanos % NANOS_PER_SECOND) as u32))
+}
+
+fn add(total: u128, nanos: u128, input: Token) -> Result<u128, ParseTimeError> {
+    total.checked_add(nanos).ok_or_else(|| input.out_of_range())
+}
+
+fn unit_seconds(unit: &str) -> Option<u64> {
+    UNITS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(unit))
+        .map(|(_, seconds)| *seconds)
+}
+
+/// The known unit `unit` is a typo of, if it is close enough to one to guess.
+fn closest_unit(unit: &str) -> Option<&'static str> {
+    let unit = unit.to_ascii_lowercase();
+    UNITS
+        .iter()
+        .map(|(candidate, _)| (edit_distance(&unit, candidate), *candidate))
+        .filter(|(distance, _)| *distance > 0 && *distance <= 2 && *distance < unit.len())
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+fn split_number<'a>(text: Token<'a>) -> (Token<'a>, Token<'a>) {
+    let end = text
+        .as_str()
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    text.split_at(end)
+}
+
+fn parse_units(input: Token) -> Result<Duration, ParseTimeError> {
+    let mut total = 0;
+    let mut seen = Vec::new();
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let (number, after) = split_number(rest);
+        if number.is_empty() {
+            let word = rest
+                .as_str()
+                .split(|c: char| c.is_whitespace() || c.is_ascii_digit())
+                .next()
+                .unwrap_or(rest.as_str());
+            return Err(rest.slice(0..word.len()).invalid_char());
+        }
+
+        let after = after.trim_start();
+        let unit_end = after
+            .as_str()
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(after.len());
+        let (unit, after) = after.split_at(unit_end);
+
+        // `1h 30` could mean minutes or seconds, minutes after hours is the likelier
+        if unit.is_empty() {
+            let missing = match seen.last() {
+                Some(&SECONDS_PER_HOUR) | None => Some("m"),
+                Some(&SECONDS_PER_MINUTE) => Some("s"),
+                Some(_) => None,
+            };
+            return Err(number.ambiguous(missing));
+        }
+        let seconds = unit_seconds(unit.as_str()).ok_or_else(|| unit.invalid_unit())?;
+        // `1m 2m` is more likely a typo than three minutes
+        if seen.contains(&seconds) {
+            return Err(number.join(unit).ambiguous(None));
+        }
+        seen.push(seconds);
+
+        total = add(
+            total,
+            scaled(parse_number(number)?, seconds, number)?,
+            input,
+        )?;
+        rest = after.trim_start();
     }
+
+    to_duration(total, input)
+}
+
+fn parse_clock(input: Token) -> Result<Duration, ParseTimeError> {
+    let parts = input.split(':');
+    let (hours, minutes, seconds) = match parts[..] {
+        [minutes, seconds] => (None, minutes, seconds),
+        [hours, minutes, seconds] => (Some(hours), minutes, seconds),
+        _ => {
+            // Point at the first colon too many
+            let extra = input
+                .as_str()
+                .match_indices(':')
+                .nth(2)
+                .map_or(0, |(at, _)| at);
+            return Err(input.slice(extra..extra + 1).invalid_char());
+        }
+    };
+
+    let field = |part: Token, limit: Option<u64>| -> Result<u64, ParseTimeError> {
+        let text = part.as_str();
+        if text.chars().any(|c| !c.is_ascii_digit()) && !text.is_empty() {
+            return Err(part.invalid_char());
+        }
+        let value = text.parse::<u64>().map_err(|e| match e.kind() {
+            IntErrorKind::PosOverflow => part.out_of_range(),
+            _ => ParseTimeError::IntError {
+                source: e,
+                span: part.span(),
+            },
+        })?;
+        match limit {
+            Some(limit) if value >= limit => Err(part.out_of_range()),
+            _ => Ok(value),
+        }
+    };
+
+    let mut total = u128::from(field(seconds, Some(60))?) * NANOS_PER_SECOND;
+    let minutes = field(minutes, hours.map(|_| 60))?;
+    total = add(
+        total,
+        scaled(whole(minutes), SECONDS_PER_MINUTE, input)?,
+        input,
+    )?;
+    if let Some(hours) = hours {
+        total = add(
+            total,
+            scaled(whole(field(hours, None)?), SECONDS_PER_HOUR, input)?,
+            input,
+        )?;
+    }
+
+    to_duration(total, input)
+}
+
+fn whole(value: u64) -> Number {
+    Number {
+        digits: u128::from(value),
+        scale: 0,
+    }
+}
+
+/// Parses an ISO-8601 duration such as `PT1H30M` or `P1DT2H`.
+fn parse_iso8601(input: Token) -> Result<Duration, ParseTimeError> {
+    let body = input.slice(1..input.len());
+    let (date, time) = match body.as_str().find(['T', 't']) {
+        Some(at) => (body.slice(0..at), body.slice(at + 1..body.len())),
+        None => (body, body.slice(body.len()..body.len())),
+    };
+    if date.is_empty() && time.is_empty() {
+        return Err(ParseTimeError::Empty { span: input.span() });
+    }
+
+    // Months and years have no fixed length
+    let date_total = parse_designators(
+        date,
+        &[('W', SECONDS_PER_WEEK), ('D', SECONDS_PER_DAY)],
+        &['Y', 'M'],
+        "D",
+    )?;
+    let time_total = parse_designators(
+        time,
+        &[('H', SECONDS_PER_HOUR), ('M', SECONDS_PER_MINUTE), ('S', 1)],
+        &[],
+        "M",
+    )?;
+
+    to_duration(add(date_total, time_total, input)?, input)
+}
+
+/// `missing` is the designator to suggest for a trailing bare number.
+fn parse_designators(
+    section: Token,
+    units: &[(char, u64)],
+    ambiguous: &[char],
+    missing: &'static str,
+) -> Result<u128, ParseTimeError> {
+    let mut total = 0;
+    let mut next = 0;
+    let mut rest = section;
+
+    while !rest.is_empty() {
+        let (number, after) = split_number(rest);
+        let Some(letter) = after.as_str().chars().next() else {
+            return Err(number.ambiguous(Some(missing)));
+        };
+        if number.is_empty() {
+            return Err(rest.invalid_char());
+        }
+        let (designator, after) = after.split_at(letter.len_utf8());
+        let letter = letter.to_ascii_uppercase();
+        if ambiguous.contains(&letter) {
+            return Err(number.join(designator).ambiguous(None));
+        }
+
+        // Designators come in a fixed order, each at most once
+        let position = units[next..]
+            .iter()
+            .position(|(unit, _)| *unit == letter)
+            .ok_or_else(|| designator.invalid_unit())?;
+        let (_, seconds) = units[next + position];
+        next += position + 1;
+
+        total = add(
+            total,
+            scaled(parse_number(number)?, seconds, number)?,
+            section,
+        )?;
+        rest = after;
+    }
+
+    Ok(total)
 }
 
 #[cfg(test)]
@@ -100,19 +646,205 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_time_of_day() {
+        assert_eq!(
+            parse_time_of_day("15:30").unwrap(),
+            NaiveTime::from_hms_opt(15, 30, 0).unwrap()
+        );
+        assert_eq!(
+            parse_time_of_day("9:05").unwrap(),
+            NaiveTime::from_hms_opt(9, 5, 0).unwrap()
+        );
+        for input in ["24:00", "15", "3pm", "15:30:00"] {
+            assert!(matches!(
+                parse_time_of_day(input),
+                Err(ParseTimeError::InvalidTimeOfDay { .. })
+            ));
+        }
+    }
+
     #[test]
     fn test_invalid() {
         assert!(matches!(
             parse_duration("1ma"),
-            Err(ParseTimeError::InvalidUnit(_))
+            Err(ParseTimeError::InvalidUnit { .. })
         ));
         assert!(matches!(
             parse_duration("am"),
-            Err(ParseTimeError::InvalidChar(_))
+            Err(ParseTimeError::InvalidChar { .. })
+        ));
+        assert!(matches!(
+            parse_duration("1:3a"),
+            Err(ParseTimeError::InvalidChar { .. })
+        ));
+        assert!(matches!(
+            parse_duration("1::00"),
+            Err(ParseTimeError::IntError { .. })
+        ));
+        assert_eq!(
+            parse_duration("  "),
+            Err(ParseTimeError::Empty { span: 0..2 })
+        );
+        assert_eq!(
+            parse_duration(" PT"),
+            Err(ParseTimeError::Empty { span: 1..3 })
+        );
+    }
+
+    #[test]
+    fn test_forms() {
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+
+        assert_eq!(parse_duration("90").unwrap(), minutes(90));
+        assert_eq!(parse_duration("1.5").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("1.5h").unwrap(), minutes(90));
+        assert_eq!(parse_duration("0.5m").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("1h 30m").unwrap(), minutes(90));
+        assert_eq!(parse_duration("30m 1h").unwrap(), minutes(90));
+        assert_eq!(parse_duration("30sec").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("2 minutes").unwrap(), minutes(2));
+        assert_eq!(parse_duration("1 hour 5 mins").unwrap(), minutes(65));
+        assert_eq!(parse_duration("25:00").unwrap(), minutes(25));
+        assert_eq!(
+            parse_duration("90:30").unwrap(),
+            Duration::from_secs(90 * 60 + 30)
+        );
+        assert_eq!(parse_duration("1:30:00").unwrap(), minutes(90));
+        assert_eq!(parse_duration("0.001s").unwrap(), Duration::from_millis(1));
+    }
+
+    #[test]
+    fn test_iso8601() {
+        assert_eq!(
+            parse_duration("PT25M").unwrap(),
+            Duration::from_secs(25 * 60)
+        );
+        assert_eq!(
+            parse_duration("PT1H30M").unwrap(),
+            Duration::from_secs(90 * 60)
+        );
+        assert_eq!(
+            parse_duration("pt0.5h").unwrap(),
+            Duration::from_secs(30 * 60)
+        );
+        assert_eq!(
+            parse_duration("P1DT2H").unwrap(),
+            Duration::from_secs(26 * 3600)
+        );
+        assert_eq!(
+            parse_duration("P1W").unwrap(),
+            Duration::from_secs(7 * 86400)
+        );
+
+        assert!(matches!(
+            parse_duration("P1M"),
+            Err(ParseTimeError::Ambiguous { .. })
         ));
         assert!(matches!(
-            parse_duration("0.5m"),
-            Err(ParseTimeError::IntError(_))
+            parse_duration("PT30M1H"),
+            Err(ParseTimeError::InvalidUnit { .. })
         ));
+        assert!(matches!(
+            parse_duration("PT25"),
+            Err(ParseTimeError::Ambiguous { .. })
+        ));
+    }
+
+    #[test]
+    fn test_ambiguous_and_out_of_range() {
+        assert!(matches!(
+            parse_duration("1h 30"),
+            Err(ParseTimeError::Ambiguous { .. })
+        ));
+        assert!(matches!(
+            parse_duration("1m 2m"),
+            Err(ParseTimeError::Ambiguous { .. })
+        ));
+        assert!(matches!(
+            parse_duration("1:60:00"),
+            Err(ParseTimeError::OutOfRange { .. })
+        ));
+        assert!(matches!(
+            parse_duration("25:60"),
+            Err(ParseTimeError::OutOfRange { .. })
+        ));
+
+        // `u64::MAX` hours used to overflow on multiplication
+        for input in [
+            "18446744073709551615h",
+            "99999999999999999999999999999999999999999h",
+            "18446744073709551615:00:00",
+            "0.0000000001s",
+        ] {
+            assert!(
+                matches!(
+                    parse_duration(input),
+                    Err(ParseTimeError::OutOfRange { .. })
+                ),
+                "{input}"
+            );
+        }
+        assert_eq!(
+            parse_duration("18446744073709551615s").unwrap(),
+            Duration::from_secs(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_named_duration() {
+        assert_eq!(
+            parse_named_duration("tea 4m").unwrap(),
+            ("tea", Duration::from_secs(4 * 60))
+        );
+        assert_eq!(
+            parse_named_duration("build  1h 30m ").unwrap(),
+            ("build", Duration::from_secs(90 * 60))
+        );
+        assert_eq!(
+            parse_named_duration("25").unwrap(),
+            ("", Duration::from_secs(25 * 60))
+        );
+
+        // The error points at the last word, where the duration should be
+        let error = parse_named_duration("tea 4mm").unwrap_err();
+        assert_eq!(error.span(), 5..7);
+        assert_eq!(error.suggestion("tea 4mm").as_deref(), Some("tea 4m"));
+        assert!(matches!(
+            parse_named_duration(" "),
+            Err(ParseTimeError::Empty { .. })
+        ));
+    }
+
+    #[test]
+    fn test_spans_and_suggestions() {
+        let error = parse_duration(" 25mm").unwrap_err();
+        assert_eq!(error.span(), 3..5);
+        assert_eq!(error.suggestion(" 25mm").as_deref(), Some(" 25m"));
+
+        let span = |input| parse_duration(input).unwrap_err().span();
+        assert_eq!(span("1h 2x 3s"), 4..5);
+        assert_eq!(span("1m 2 m"), 3..6);
+        assert_eq!(span("1:2:3:4"), 5..6);
+        assert_eq!(span("1:60:00"), 2..4);
+        assert_eq!(span("PT30M1H"), 6..7);
+        assert_eq!(span("18446744073709551615:00:00"), 0..26);
+
+        let suggest = |input| parse_duration(input).unwrap_err().suggestion(input);
+        assert_eq!(suggest("1h 30").as_deref(), Some("1h 30m"));
+        assert_eq!(suggest("1 hrz").as_deref(), Some("1 hr"));
+        assert_eq!(suggest("2 mintes").as_deref(), Some("2 minutes"));
+        assert_eq!(suggest("PT25").as_deref(), Some("PT25M"));
+        assert_eq!(suggest("25x"), None);
+        assert_eq!(suggest("1m 2m"), None);
+
+        // A speaker's timebox is parsed out of `name:timebox`
+        let error = parse_duration("2mm").unwrap_err().offset(6);
+        assert_eq!(
+            Diagnostic::new("alice:2mm", error).to_string(),
+            "Invalid unit format: `mm`, Unit must be one of h, hr(s), hour(s), m, min(s), \
+             minute(s), s, sec(s) or second(s)\n\n    \
+             alice:2mm\n           ^^ did you mean `alice:2m`?"
+        );
     }
 }
@@ -0,0 +1,46 @@
+use std::env;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// Where what outlives a run is kept, such as checkpoints and team statistics, so it's found
+/// again from whichever directory pomoduro is started in.
+pub fn state_dir() -> PathBuf {
+    state_dir_from(env::var_os("XDG_STATE_HOME"), env::var_os("HOME"))
+}
+
+/// `$XDG_STATE_HOME/pomoduro`, falling back to `~/.local/state/pomoduro` when it's unset or not
+/// an absolute path, as the XDG spec asks.
+fn state_dir_from(xdg_state_home: Option<OsString>, home: Option<OsString>) -> PathBuf {
+    let base = xdg_state_home
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| home.map(|home| PathBuf::from(home).join(".local/state")))
+        .unwrap_or_default();
+    base.join("pomoduro")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_dir() {
+        let dir = |xdg: Option<&str>, home: Option<&str>| {
+            state_dir_from(xdg.map(OsString::from), home.map(OsString::from))
+        };
+
+        assert_eq!(
+            dir(Some("/var/state"), Some("/home/ann")),
+            PathBuf::from("/var/state/pomoduro")
+        );
+        assert_eq!(
+            dir(None, Some("/home/ann")),
+            PathBuf::from("/home/ann/.local/state/pomoduro")
+        );
+        // A relative XDG path is ignored
+        assert_eq!(
+            dir(Some("state"), Some("/home/ann")),
+            PathBuf::from("/home/ann/.local/state/pomoduro")
+        );
+    }
+}
@@ -0,0 +1,531 @@
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::clock::{self, SharedClock};
+use crate::parser::parse_duration;
+use crate::session::{Session, SessionSnapshot, SnapshotMismatch};
+use crate::timer::{Timer, TimerSnapshot, TimerStatus};
+
+#[derive(Error, Debug)]
+pub enum PlanError {
+    #[error("Could not read the plan: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("{}", describe(*line, message))]
+    Invalid {
+        line: Option<usize>,
+        message: String,
+    },
+
+    #[error("The plan has no steps")]
+    Empty,
+
+    #[error("Plans must be .toml, .yaml or .yml files")]
+    UnknownFormat,
+}
+
+fn describe(line: Option<usize>, message: &str) -> String {
+    match line {
+        Some(line) => format!("line {}: {}", line, message),
+        None => message.to_string(),
+    }
+}
+
+/// What happens when a step runs out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Advance {
+    /// The next step starts right away
+    #[default]
+    Auto,
+    /// The next step waits for a key press
+    Manual,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Step {
+    pub name: String,
+    pub duration: Duration,
+    pub advance: Advance,
+}
+
+/// A plan file as written, before repeated steps are spelled out.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlanFile {
+    title: Option<String>,
+    steps: Vec<StepEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StepEntry {
+    name: String,
+    duration: PlanDuration,
+    #[serde(default)]
+    repeat: Repeat,
+    #[serde(default)]
+    advance: Advance,
+}
+
+/// Steps are spelled out when the plan loads, so a step repeats at most this many times.
+const MAX_REPEAT: usize = 1000;
+
+/// How many times a step runs in a row. Checked while deserializing so a bad count is reported
+/// at its line.
+struct Repeat(usize);
+
+impl Default for Repeat {
+    fn default() -> Self {
+        Repeat(1)
+    }
+}
+
+impl<'de> Deserialize<'de> for Repeat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RepeatVisitor;
+
+        impl Visitor<'_> for RepeatVisitor {
+            type Value = Repeat;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a number of times between 1 and {}", MAX_REPEAT)
+            }
+
+            fn visit_u64<E: de::Error>(self, repeat: u64) -> Result<Repeat, E> {
+                match usize::try_from(repeat) {
+                    Ok(repeat @ 1..=MAX_REPEAT) => Ok(Repeat(repeat)),
+                    _ => Err(E::custom(format!(
+                        "repeat must be between 1 and {}",
+                        MAX_REPEAT
+                    ))),
+                }
+            }
+
+            fn visit_i64<E: de::Error>(self, repeat: i64) -> Result<Repeat, E> {
+                self.visit_u64(u64::try_from(repeat).unwrap_or(0))
+            }
+        }
+
+        deserializer.deserialize_u64(RepeatVisitor)
+    }
+}
+
+/// A duration written any way `parse_duration` reads, or as a plain number of minutes. Parsing
+/// it while deserializing lets a bad one be reported at its line.
+struct PlanDuration(Duration);
+
+impl<'de> Deserialize<'de> for PlanDuration {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DurationVisitor;
+
+        impl Visitor<'_> for DurationVisitor {
+            type Value = PlanDuration;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a duration such as \"25m\" or a number of minutes")
+            }
+
+            fn visit_str<E: de::Error>(self, input: &str) -> Result<PlanDuration, E> {
+                parse_duration(input)
+                    .map(PlanDuration)
+                    .map_err(|e| E::custom(format!("invalid duration `{}`: {}", input, e)))
+            }
+
+            fn visit_u64<E: de::Error>(self, minutes: u64) -> Result<PlanDuration, E> {
+                self.visit_str(&minutes.to_string())
+            }
+
+            fn visit_i64<E: de::Error>(self, minutes: i64) -> Result<PlanDuration, E> {
+                self.visit_str(&minutes.to_string())
+            }
+
+            fn visit_f64<E: de::Error>(self, minutes: f64) -> Result<PlanDuration, E> {
+                self.visit_str(&minutes.to_string())
+            }
+        }
+
+        deserializer.deserialize_any(DurationVisitor)
+    }
+}
+
+impl PlanFile {
+    fn into_plan(self, clock: SharedClock) -> Result<Plan, PlanError> {
+        let steps: Vec<Step> = self
+            .steps
+            .into_iter()
+            .flat_map(|entry| {
+                (1..=entry.repeat.0).map(move |round| Step {
+                    name: match entry.repeat.0 {
+                        1 => entry.name.clone(),
+                        total => format!("{} ({}/{})", entry.name, round, total),
+                    },
+                    duration: entry.duration.0,
+                    advance: entry.advance,
+                })
+            })
+            .collect();
+
+        if steps.is_empty() {
+            return Err(PlanError::Empty);
+        }
+        Ok(Plan::new(self.title, steps, clock))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlanSnapshot {
+    pub title: Option<String>,
+    pub steps: Vec<Step>,
+    pub current: usize,
+    pub waiting: bool,
+    pub completed: bool,
+    pub timer: TimerSnapshot,
+}
+
+/// A sequence of named timers run one after the other, e.g. a workshop agenda or interval
+/// training, loaded from a TOML or YAML file.
+pub struct Plan {
+    title: Option<String>,
+    steps: Vec<Step>,
+    current: usize,
+    /// The current step ran out and waits for a key press to move on
+    waiting: bool,
+    completed: bool,
+    timer: Timer,
+}
+
+impl Plan {
+    /// `steps` must not be empty.
+    pub fn new(title: Option<String>, steps: Vec<Step>, clock: SharedClock) -> Self {
+        let timer = step_timer(&steps[0], clock);
+        Plan {
+            title,
+            steps,
+            current: 0,
+            waiting: false,
+            completed: false,
+            timer,
+        }
+    }
+
+    /// Reads a plan from a `.toml`, `.yaml` or `.yml` file.
+    pub fn load(path: &Path) -> Result<Self, PlanError> {
+        let source = fs::read_to_string(path)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Plan::from_toml(&source, clock::system_clock()),
+            Some("yaml" | "yml") => Plan::from_yaml(&source, clock::system_clock()),
+            _ => Err(PlanError::UnknownFormat),
+        }
+    }
+
+    pub fn from_toml(source: &str, clock: SharedClock) -> Result<Self, PlanError> {
+        let file: PlanFile = toml::from_str(source).map_err(|e| PlanError::Invalid {
+            line: e
+                .span()
+                .map(|span| source[..span.start].matches('\n').count() + 1),
+            message: e.message().to_string(),
+        })?;
+        file.into_plan(clock)
+    }
+
+    pub fn from_yaml(source: &str, clock: SharedClock) -> Result<Self, PlanError> {
+        let file: PlanFile = serde_yaml::from_str(source).map_err(|e| {
+            let message = e.to_string();
+            // The location is reported separately
+            let message = match message.split_once(" at line ") {
+                Some((message, _)) => message.to_string(),
+                None => message,
+            };
+            PlanError::Invalid {
+                line: e.location().map(|location| location.line()),
+                message,
+            }
+        })?;
+        file.into_plan(clock)
+    }
+
+    pub fn snapshot(&self) -> PlanSnapshot {
+        PlanSnapshot {
+            title: self.title.clone(),
+            steps: self.steps.clone(),
+            current: self.current,
+            waiting: self.waiting,
+            completed: self.completed,
+            timer: self.timer.snapshot(),
+        }
+    }
+
+    pub fn restore(snapshot: PlanSnapshot, clock: SharedClock) -> Self {
+        Plan {
+            title: snapshot.title,
+            steps: snapshot.steps,
+            current: snapshot.current,
+            waiting: snapshot.waiting,
+            completed: snapshot.completed,
+            timer: Timer::restore(snapshot.timer, clock),
+        }
+    }
+
+    pub fn tick(&mut self) {
+        if self.completed || self.waiting {
+            return;
+        }
+
+        if self.timer.get_status() == TimerStatus::Exit {
+            self.completed = true;
+        } else if self.timer.is_done() {
+            match self.steps[self.current].advance {
+                Advance::Auto => self.start_next(),
+                Advance::Manual if self.is_last_step() => self.completed = true,
+                Advance::Manual => self.waiting = true,
+            }
+        }
+    }
+
+    /// Moves on from a step that waits for it.
+    pub fn next(&mut self) {
+        if self.waiting {
+            self.start_next();
+        }
+    }
+
+    pub fn skip(&mut self) {
+        if !self.completed {
+            self.start_next();
+        }
+    }
+
+    pub fn finish(&mut self) {
+        self.completed = true;
+    }
+
+    fn start_next(&mut self) {
+        self.waiting = false;
+        if self.is_last_step() {
+            self.completed = true;
+            return;
+        }
+
+        self.current += 1;
+        self.timer = step_timer(&self.steps[self.current], self.timer.get_clock());
+    }
+
+    fn is_last_step(&self) -> bool {
+        self.current + 1 >= self.steps.len()
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    pub fn is_waiting(&self) -> bool {
+        self.waiting
+    }
+
+    pub fn get_title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn get_steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Index of the step running now, or the one waiting to move on.
+    pub fn get_current(&self) -> usize {
+        self.current
+    }
+
+    pub fn get_upcoming(&self) -> &[Step] {
+        match self.completed {
+            true => &[],
+            false => &self.steps[self.current + 1..],
+        }
+    }
+
+    /// What is left of the current step and all the steps after it.
+    pub fn total_remaining(&self) -> Duration {
+        match self.completed {
+            true => Duration::ZERO,
+            false => self
+                .get_upcoming()
+                .iter()
+                .map(|step| step.duration)
+                .fold(self.timer.remaining_time(), Duration::saturating_add),
+        }
+    }
+
+    pub fn get_timer(&mut self) -> &mut Timer {
+        &mut self.timer
+    }
+}
+
+fn step_timer(step: &Step, clock: SharedClock) -> Timer {
+    Timer::builder(step.duration)
+        .name(step.name.clone())
+        .clock(clock)
+        .build()
+}
+
+pub struct PlanSession {
+    plan: Plan,
+}
+
+impl PlanSession {
+    pub fn new(plan: Plan) -> Self {
+        PlanSession { plan }
+    }
+
+    pub fn restore(snapshot: PlanSnapshot) -> Self {
+        PlanSession {
+            plan: Plan::restore(snapshot, clock::system_clock()),
+        }
+    }
+}
+
+impl Session for PlanSession {
+    fn tick(&mut self) {
+        self.plan.tick();
+    }
+
+    fn is_finished(&self) -> bool {
+        self.plan.is_completed()
+    }
+
+    fn toggle_pause(&mut self) {
+        self.plan.timer.toggle_pause();
+    }
+
+    fn get_timer(&mut self) -> Option<&mut Timer> {
+        Some(self.plan.get_timer())
+    }
+
+    fn get_plan(&mut self) -> Option<&mut Plan> {
+        Some(&mut self.plan)
+    }
+
+    fn snapshot(&self) -> SessionSnapshot {
+        SessionSnapshot::Plan(self.plan.snapshot())
+    }
+
+    fn restore(&mut self, snapshot: SessionSnapshot) -> Result<(), SnapshotMismatch> {
+        match snapshot {
+            SessionSnapshot::Plan(snapshot) => {
+                let clock = self.plan.timer.get_clock();
+                self.plan = Plan::restore(snapshot, clock);
+                Ok(())
+            }
+            _ => Err(SnapshotMismatch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use std::sync::Arc;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    const WORKSHOP: &str = r#"
+title = "Workshop"
+
+[[steps]]
+name = "Intro"
+duration = "10m"
+
+[[steps]]
+name = "Exercise"
+duration = 20
+repeat = 2
+advance = "manual"
+"#;
+
+    fn run(plan: &mut Plan, clock: &ManualClock, duration: Duration) {
+        clock.advance(duration);
+        plan.tick();
+    }
+
+    #[test]
+    fn test_run_plan() {
+        let clock = Arc::new(ManualClock::new());
+        let mut plan = Plan::from_toml(WORKSHOP, clock.clone()).unwrap();
+
+        assert_eq!(plan.get_title(), Some("Workshop"));
+        let names: Vec<&str> = plan.get_steps().iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["Intro", "Exercise (1/2)", "Exercise (2/2)"]);
+        assert_eq!(plan.total_remaining(), 50 * MINUTE);
+
+        // Intro moves on by itself, the exercises wait to be moved on from
+        run(&mut plan, &clock, 10 * MINUTE);
+        assert_eq!(plan.get_current(), 1);
+        assert_eq!(plan.get_upcoming().len(), 1);
+        run(&mut plan, &clock, 20 * MINUTE);
+        assert!(plan.is_waiting());
+        run(&mut plan, &clock, 5 * MINUTE);
+        assert_eq!(plan.get_current(), 1);
+        assert_eq!(plan.total_remaining(), 20 * MINUTE);
+
+        plan.next();
+        assert_eq!(plan.get_current(), 2);
+        let restored = Plan::restore(plan.snapshot(), clock.clone());
+        assert_eq!(restored.get_current(), 2);
+
+        plan.skip();
+        assert!(plan.is_completed());
+        assert_eq!(plan.total_remaining(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_yaml() {
+        let yaml = "steps:\n  - name: Warm up\n    duration: 5m\n  - name: Sprint\n    duration: 30s\n    repeat: 4\n";
+        let plan = Plan::from_yaml(yaml, Arc::new(ManualClock::new())).unwrap();
+        assert_eq!(plan.get_steps().len(), 5);
+        assert_eq!(plan.total_remaining(), 7 * MINUTE);
+
+        // Steps too long to add up stop at the longest duration there is
+        let yaml = "steps:\n  - name: Long\n    duration: 18446744073709551615s\n    repeat: 2\n";
+        let plan = Plan::from_yaml(yaml, Arc::new(ManualClock::new())).unwrap();
+        assert_eq!(plan.total_remaining(), Duration::MAX);
+    }
+
+    #[test]
+    fn test_errors_have_lines() {
+        let clock = || Arc::new(ManualClock::new());
+        let line = |error: PlanError| match error {
+            PlanError::Invalid { line, .. } => line,
+            _ => None,
+        };
+
+        let toml = "[[steps]]\nname = \"Intro\"\nduration = \"10mm\"\n";
+        let error = Plan::from_toml(toml, clock()).err().unwrap();
+        assert!(
+            error.to_string().contains("invalid duration `10mm`"),
+            "{error}"
+        );
+        assert_eq!(line(error), Some(3));
+
+        let yaml = "steps:\n  - name: Intro\n    duration: 10m\n    repeat: 0\n";
+        let error = Plan::from_yaml(yaml, clock()).err().unwrap();
+        assert!(error.to_string().starts_with("line 4: "), "{error}");
+
+        let yaml = "steps:\n  - name: Intro\n    duration: 10m\n    repeat: 1000000000\n";
+        let error = Plan::from_yaml(yaml, clock()).err().unwrap();
+        assert!(error.to_string().contains("between 1 and 1000"), "{error}");
+
+        let yaml = "steps:\n  - name: Intro\n    durtion: 10m\n";
+        assert_eq!(line(Plan::from_yaml(yaml, clock()).err().unwrap()), Some(3));
+
+        assert!(matches!(
+            Plan::from_toml("steps = []", clock()),
+            Err(PlanError::Empty)
+        ));
+    }
+}
@@ -1,9 +1,11 @@
-use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::app::Session;
-use crate::timer::{Timer, TimerStatus};
+use crate::clock::{self, SharedClock};
+use crate::session::{Session, SessionSnapshot, SnapshotMismatch};
+use crate::timer::{Timer, TimerSnapshot, TimerStatus};
 
-#[derive(PartialEq, Clone, Copy)]
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug)]
 pub enum PomodoroState {
     Ready,
     Focus(usize),
@@ -12,101 +14,599 @@ pub enum PomodoroState {
     Completed,
 }
 
+impl PomodoroState {
+    /// The focus session this phase belongs to, counted from 1 across the whole day.
+    pub fn session(self) -> Option<usize> {
+        match self {
+            PomodoroState::Focus(session)
+            | PomodoroState::Break(session)
+            | PomodoroState::LongBreak(session) => Some(session),
+            PomodoroState::Ready | PomodoroState::Completed => None,
+        }
+    }
+
+    /// Where this phase falls within a set of `long_break_every` sessions, counted from 1.
+    pub fn set_position(self, long_break_every: usize) -> Option<usize> {
+        self.session().map(|session| match long_break_every {
+            0 => session,
+            every => (session - 1) % every + 1,
+        })
+    }
+}
+
+/// Which phases start on their own when the one before ends; the others wait at `Ready` for a
+/// key press.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AutoStart {
+    pub focus: bool,
+    pub breaks: bool,
+}
+
+impl Default for AutoStart {
+    fn default() -> Self {
+        AutoStart {
+            focus: true,
+            breaks: true,
+        }
+    }
+}
+
+/// How long a phase lasts in each session: the same every time, or growing from one session to
+/// the next, e.g. to warm up with short focus sessions.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Lengths {
+    Fixed(Duration),
+    /// One per session, the last one repeating once they run out
+    List(Vec<Duration>),
+    /// `start` for the first session and `step` longer for each one after it, up to `max`
+    Ramp {
+        start: Duration,
+        step: Duration,
+        max: Option<Duration>,
+    },
+}
+
+impl Lengths {
+    /// The length for a session, counted from 1.
+    pub fn get(&self, session: usize) -> Duration {
+        let index = session.saturating_sub(1);
+        match self {
+            Lengths::Fixed(length) => *length,
+            Lengths::List(lengths) => lengths
+                .get(index)
+                .or(lengths.last())
+                .copied()
+                .unwrap_or_default(),
+            Lengths::Ramp { start, step, max } => {
+                let grown = step.saturating_mul(u32::try_from(index).unwrap_or(u32::MAX));
+                let length = start.saturating_add(grown);
+                max.map_or(length, |max| length.min(max))
+            }
+        }
+    }
+}
+
+impl From<Duration> for Lengths {
+    fn from(length: Duration) -> Self {
+        Lengths::Fixed(length)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PomodoroSnapshot {
+    pub state: PomodoroState,
+    pub total_sessions: usize,
+    #[serde(default = "default_long_break_every")]
+    pub long_break_every: usize,
+    #[serde(default)]
+    pub completed: usize,
+    pub focus_duration: Lengths,
+    pub break_duration: Lengths,
+    pub long_break_duration: Duration,
+    pub timer: Option<TimerSnapshot>,
+    #[serde(default)]
+    pub pending: Option<PomodoroState>,
+    #[serde(default)]
+    pub waited: Duration,
+    #[serde(default)]
+    pub auto_start: AutoStart,
+    #[serde(default)]
+    pub ends_at: Option<SystemTime>,
+    #[serde(default)]
+    pub overtime: bool,
+    #[serde(default)]
+    pub overrun: Duration,
+}
+
+const DEFAULT_SESSIONS: usize = 4;
+const DEFAULT_LONG_BREAK_EVERY: usize = 4;
+const DEFAULT_FOCUS_DURATION: Duration = Duration::from_secs(25 * 60);
+const DEFAULT_BREAK_DURATION: Duration = Duration::from_secs(5 * 60);
+const DEFAULT_LONG_BREAK_DURATION: Duration = Duration::from_secs(15 * 60);
+
+fn default_long_break_every() -> usize {
+    DEFAULT_LONG_BREAK_EVERY
+}
+
+/// Builds a `Pomodoro`, starting from four sessions of 25 minutes with 5 minute breaks and a
+/// 15 minute long break, e.g. `Pomodoro::builder().focus(Duration::from_secs(50 * 60)).build()`.
+pub struct PomodoroBuilder {
+    total_sessions: usize,
+    long_break_every: usize,
+    focus_duration: Lengths,
+    break_duration: Lengths,
+    long_break_duration: Duration,
+    auto_start: AutoStart,
+    fit_within: Option<Duration>,
+    overtime: bool,
+    clock: SharedClock,
+}
+
+impl PomodoroBuilder {
+    /// Completes after this many focus sessions, the daily target; 0 keeps cycling until stopped.
+    pub fn sessions(mut self, total_sessions: usize) -> Self {
+        self.total_sessions = total_sessions;
+        self
+    }
+
+    pub fn continuous(self) -> Self {
+        self.sessions(0)
+    }
+
+    /// Takes a long break instead of a short one after every this many focus sessions, 0 never
+    /// does.
+    pub fn long_break_every(mut self, sessions: usize) -> Self {
+        self.long_break_every = sessions;
+        self
+    }
+
+    /// One length for every focus session, or `Lengths` that change from session to session.
+    pub fn focus(mut self, lengths: impl Into<Lengths>) -> Self {
+        self.focus_duration = lengths.into();
+        self
+    }
+
+    pub fn short_break(mut self, lengths: impl Into<Lengths>) -> Self {
+        self.break_duration = lengths.into();
+        self
+    }
+
+    pub fn long_break(mut self, duration: Duration) -> Self {
+        self.long_break_duration = duration;
+        self
+    }
+
+    pub fn auto_start(mut self, auto_start: AutoStart) -> Self {
+        self.auto_start = auto_start;
+        self
+    }
+
+    /// Fits as many sessions as start within `available`, cutting the last phase short so the
+    /// pomodoro ends right on time. Replaces the daily target.
+    pub fn fit_within(mut self, available: Duration) -> Self {
+        self.fit_within = Some(available);
+        self
+    }
+
+    /// Keeps each phase counting past zero until dismissed instead of moving on.
+    pub fn overtime(mut self, overtime: bool) -> Self {
+        self.overtime = overtime;
+        self
+    }
+
+    pub fn clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// How many focus sessions start before `available` runs out.
+    fn sessions_within(&self, available: Duration) -> usize {
+        let mut sessions = 0;
+        let mut planned = Duration::ZERO;
+        while planned < available {
+            sessions += 1;
+            let cycle =
+                self.focus_duration
+                    .get(sessions)
+                    .saturating_add(match self.long_break_every {
+                        every if every > 0 && sessions % every == 0 => self.long_break_duration,
+                        _ => self.break_duration.get(sessions),
+                    });
+            if cycle.is_zero() {
+                break;
+            }
+            planned = planned.saturating_add(cycle);
+        }
+        sessions.max(1)
+    }
+
+    pub fn build(self) -> Pomodoro {
+        let total_sessions = match self.fit_within {
+            Some(available) => self.sessions_within(available),
+            None => self.total_sessions,
+        };
+        let first_focus = self.focus_duration.get(1);
+        let timer = Timer::with_clock(first_focus, "Focus".to_string(), self.clock);
+        let mut pomodoro = Pomodoro::new(
+            total_sessions,
+            self.focus_duration,
+            self.break_duration,
+            self.long_break_duration,
+            timer,
+        );
+        pomodoro.long_break_every = self.long_break_every;
+        pomodoro.auto_start = self.auto_start;
+        pomodoro.overtime = self.overtime;
+        if let Some(available) = self.fit_within {
+            pomodoro.ends_at = Some(pomodoro.clock.wall_now() + available);
+        }
+        pomodoro.timer = Some(pomodoro.new_timer(first_focus, "Focus"));
+        pomodoro.hold_if_manual();
+        pomodoro
+    }
+}
+
 #[derive(Clone)]
 pub struct Pomodoro {
     state: PomodoroState,
-    focus_duration: Duration,
-    break_duration: Duration,
+    focus_duration: Lengths,
+    break_duration: Lengths,
     long_break_duration: Duration,
     total_sessions: usize,
+    long_break_every: usize,
+    /// Focus sessions run to their end so far
+    completed: usize,
     timer: Option<Timer>,
+    auto_start: AutoStart,
+    /// The phase waiting to be started while at `Ready`
+    pending: Option<PomodoroState>,
+    ready_since: Option<Instant>,
+    waited: Duration,
+    /// Wall-clock time the last phase is cut short at, when fitted to a deadline
+    ends_at: Option<SystemTime>,
+    overtime: bool,
+    /// Time phases ran past their end before being dismissed
+    overrun: Duration,
+    clock: SharedClock,
 }
 
 impl Pomodoro {
     pub fn new(
         total_sessions: usize,
-        focus_duration: Duration,
-        break_duration: Duration,
+        focus_duration: impl Into<Lengths>,
+        break_duration: impl Into<Lengths>,
         long_break_duration: Duration,
         timer: Timer,
     ) -> Self {
         Pomodoro {
             // state: PomodoroState::Ready,
             state: PomodoroState::Focus(1),
-            focus_duration,
-            break_duration,
+            focus_duration: focus_duration.into(),
+            break_duration: break_duration.into(),
             long_break_duration,
             total_sessions,
+            long_break_every: DEFAULT_LONG_BREAK_EVERY,
+            completed: 0,
+            auto_start: AutoStart::default(),
+            pending: None,
+            ready_since: None,
+            waited: Duration::ZERO,
+            ends_at: None,
+            overtime: false,
+            overrun: Duration::ZERO,
+            clock: timer.get_clock(),
             timer: Some(timer),
         }
     }
 
+    pub fn builder() -> PomodoroBuilder {
+        PomodoroBuilder {
+            total_sessions: DEFAULT_SESSIONS,
+            long_break_every: DEFAULT_LONG_BREAK_EVERY,
+            focus_duration: Lengths::Fixed(DEFAULT_FOCUS_DURATION),
+            break_duration: Lengths::Fixed(DEFAULT_BREAK_DURATION),
+            long_break_duration: DEFAULT_LONG_BREAK_DURATION,
+            auto_start: AutoStart::default(),
+            fit_within: None,
+            overtime: false,
+            clock: clock::system_clock(),
+        }
+    }
+
+    pub fn snapshot(&self) -> PomodoroSnapshot {
+        PomodoroSnapshot {
+            state: self.state,
+            total_sessions: self.total_sessions,
+            long_break_every: self.long_break_every,
+            completed: self.completed,
+            focus_duration: self.focus_duration.clone(),
+            break_duration: self.break_duration.clone(),
+            long_break_duration: self.long_break_duration,
+            timer: self.timer.as_ref().map(Timer::snapshot),
+            pending: self.pending,
+            waited: self.get_waited(),
+            auto_start: self.auto_start,
+            ends_at: self.ends_at,
+            overtime: self.overtime,
+            overrun: self.overrun,
+        }
+    }
+
+    /// Rebuilds a pomodoro from a snapshot. A running timer that overran its phase finishes it
+    /// and the next phase starts from the top, with the time it overran by counted as waiting:
+    /// nobody was working through the phases while it was down.
+    pub fn restore(snapshot: PomodoroSnapshot, clock: SharedClock) -> Self {
+        let ready_since = (snapshot.state == PomodoroState::Ready).then(|| clock.now());
+        let mut pomodoro = Pomodoro {
+            state: snapshot.state,
+            focus_duration: snapshot.focus_duration,
+            break_duration: snapshot.break_duration,
+            long_break_duration: snapshot.long_break_duration,
+            total_sessions: snapshot.total_sessions,
+            long_break_every: snapshot.long_break_every,
+            completed: snapshot.completed,
+            timer: snapshot
+                .timer
+                .map(|timer| Timer::restore(timer, clock.clone())),
+            auto_start: snapshot.auto_start,
+            pending: snapshot.pending,
+            ready_since,
+            waited: snapshot.waited,
+            ends_at: snapshot.ends_at,
+            overtime: snapshot.overtime,
+            overrun: snapshot.overrun,
+            clock,
+        };
+
+        // A phase in overtime waits to be dismissed however far it overran
+        if let Some(timer) = &pomodoro.timer {
+            if timer.get_status() == TimerStatus::Running && timer.is_done() && !timer.is_overtime()
+            {
+                pomodoro.waited += timer.overrun();
+                pomodoro.finish_phase();
+            }
+        }
+        pomodoro
+    }
+
     pub fn tick(&mut self) {
         if let Some(timer) = &mut self.timer {
             if timer.get_status() == TimerStatus::Exit {
                 self.state = PomodoroState::Completed;
-            } else if timer.is_done() {
-                self.timer = self.next_timer();
+            } else if timer.is_done() && !timer.is_overtime() {
+                self.finish_phase();
             }
         }
     }
 
-    fn next_timer(&mut self) -> Option<Timer> {
-        match self.state {
-            PomodoroState::Ready => {
-                self.state = PomodoroState::Focus(1);
-                let new_timer = Timer::new(self.focus_duration, "Focus".to_string());
-                self.timer = Some(new_timer.clone());
-                Some(new_timer)
-            }
-            PomodoroState::Focus(session) if session <= self.total_sessions => {
-                self.state = PomodoroState::Break(session);
-                let new_timer = Timer::new(self.break_duration, "Break".to_string());
-                self.timer = Some(new_timer.clone());
-                Some(new_timer)
+    /// Moves on from a phase that ran to its end. Only these count as completed focus sessions,
+    /// skipping one doesn't.
+    fn finish_phase(&mut self) {
+        if self.is_focus() {
+            self.completed += 1;
+        }
+        self.advance();
+    }
+
+    fn advance(&mut self) {
+        self.timer = self.next_timer();
+
+        // Nothing more fits before the deadline
+        let out_of_time = self.ends_at.is_some()
+            && self
+                .timer
+                .as_ref()
+                .is_some_and(|timer| timer.get_duration().is_zero());
+        if out_of_time {
+            self.state = PomodoroState::Completed;
+            self.timer = None;
+            return;
+        }
+
+        self.hold_if_manual();
+    }
+
+    /// Holds a phase that just began at `Ready` if it isn't meant to start on its own.
+    fn hold_if_manual(&mut self) {
+        let manual = match self.state {
+            PomodoroState::Focus(_) => !self.auto_start.focus,
+            PomodoroState::Break(_) | PomodoroState::LongBreak(_) => !self.auto_start.breaks,
+            _ => false,
+        };
+        if !manual {
+            return;
+        }
+
+        self.pending = Some(self.state);
+        self.state = PomodoroState::Ready;
+        self.ready_since = Some(self.clock.now());
+        if let Some(timer) = &mut self.timer {
+            timer.toggle_pause();
+        }
+    }
+
+    /// Starts the phase waiting at `Ready`, adding the wait to the time spent waiting.
+    pub fn start_pending(&mut self) {
+        if self.state != PomodoroState::Ready {
+            return;
+        }
+
+        self.state = self.pending.take().unwrap_or(PomodoroState::Focus(1));
+        if let Some(since) = self.ready_since.take() {
+            self.waited += self.clock.now().saturating_duration_since(since);
+        }
+        if let Some(timer) = &mut self.timer {
+            if timer.is_paused() {
+                timer.toggle_pause();
             }
-            PomodoroState::Break(session) if session < self.total_sessions => {
-                self.state = PomodoroState::Focus(session + 1);
-                let new_timer = Timer::new(self.focus_duration, "Focus".to_string());
-                self.timer = Some(new_timer.clone());
-                Some(new_timer)
+        }
+        self.keep_to_deadline();
+    }
+
+    /// Pauses the running phase or resumes it, still ending on time after the pause.
+    pub fn toggle_pause(&mut self) {
+        if let Some(timer) = &mut self.timer {
+            timer.toggle_pause();
+            if !timer.is_paused() {
+                self.keep_to_deadline();
             }
-            PomodoroState::Break(session) if session == self.total_sessions => {
-                self.state = PomodoroState::LongBreak(session);
-                let new_timer = Timer::new(self.long_break_duration, "Long Break".to_string());
+        }
+    }
+
+    /// A phase's length is worked out when it begins, so time spent waiting at `Ready` or paused
+    /// could otherwise carry it past the deadline.
+    fn keep_to_deadline(&mut self) {
+        if let (Some(ends_at), Some(timer)) = (self.ends_at, &mut self.timer) {
+            timer.end_within(
+                ends_at
+                    .duration_since(self.clock.wall_now())
+                    .unwrap_or_default(),
+            );
+        }
+    }
+
+    fn next_timer(&mut self) -> Option<Timer> {
+        // Moving on from `Ready` skips the phase that was waiting
+        if self.state == PomodoroState::Ready {
+            self.start_pending();
+            return self.next_timer();
+        }
+
+        match self.get_next_phase() {
+            Some((state, duration)) => {
+                self.state = state;
+                let name = match state {
+                    PomodoroState::LongBreak(_) => "Long Break",
+                    PomodoroState::Break(_) => "Break",
+                    _ => "Focus",
+                };
+                let new_timer = self.new_timer(duration, name);
                 self.timer = Some(new_timer.clone());
                 Some(new_timer)
             }
-            PomodoroState::LongBreak(_) => {
+            None => {
                 self.state = PomodoroState::Completed;
                 self.timer = None;
                 None
             }
-            PomodoroState::Completed => {
-                self.timer = None;
+        }
+    }
+
+    /// The phase after the running one and how long it lasts, or `None` once the day is done.
+    pub fn get_next_phase(&self) -> Option<(PomodoroState, Duration)> {
+        match self.get_phase() {
+            PomodoroState::Focus(session)
+                if self.long_break_every > 0 && session % self.long_break_every == 0 =>
+            {
+                Some((PomodoroState::LongBreak(session), self.long_break_duration))
+            }
+            PomodoroState::Focus(session) => Some((
+                PomodoroState::Break(session),
+                self.break_duration.get(session),
+            )),
+            // The break after the last session of the target ends the day
+            PomodoroState::Break(session) | PomodoroState::LongBreak(session)
+                if self.total_sessions > 0 && session >= self.total_sessions =>
+            {
                 None
             }
-            _ => None,
+            PomodoroState::Break(session) | PomodoroState::LongBreak(session) => Some((
+                PomodoroState::Focus(session + 1),
+                self.focus_duration.get(session + 1),
+            )),
+            PomodoroState::Ready | PomodoroState::Completed => None,
+        }
+    }
+
+    /// Moves on from a phase in overtime, recording how far it overran.
+    pub fn end_overtime(&mut self) {
+        if self.is_overtime() {
+            self.overrun = self.get_overrun();
+            self.finish_phase();
+        }
+    }
+
+    fn new_timer(&self, duration: Duration, name: &str) -> Timer {
+        let duration = match self.ends_at {
+            Some(ends_at) => duration.min(
+                ends_at
+                    .duration_since(self.clock.wall_now())
+                    .unwrap_or_default(),
+            ),
+            None => duration,
+        };
+        Timer::builder(duration)
+            .name(name)
+            .overtime(self.overtime)
+            .clock(self.clock.clone())
+            .build()
+    }
+
+    /// Skipping a phase in overtime dismisses it, since it did run to its end.
+    pub fn skip_phase(&mut self) {
+        if self.is_overtime() {
+            self.end_overtime();
+        } else if !self.is_completed() {
+            self.advance();
+        }
+    }
+
+    pub fn skip_break(&mut self) {
+        if matches!(
+            self.get_phase(),
+            PomodoroState::Break(_) | PomodoroState::LongBreak(_)
+        ) {
+            self.advance();
+        }
+    }
+
+    pub fn extend_focus(&mut self, duration: Duration) {
+        if self.is_focus() {
+            if let Some(timer) = &mut self.timer {
+                timer.extend(duration);
+            }
         }
     }
 
+    pub fn start_next_session(&mut self) {
+        if self.is_focus() {
+            self.advance();
+        }
+        self.skip_break();
+        // Everyone asked for the next session, so it doesn't wait for another key press
+        self.start_pending();
+    }
+
     pub fn get_timer(&mut self) -> Option<&mut Timer> {
         self.timer.as_mut()
     }
 
     pub fn get_current_session(&self) -> usize {
-        match self.state {
-            PomodoroState::Ready => 0,
-            PomodoroState::Focus(session) | PomodoroState::Break(session) => session,
-            PomodoroState::Completed => self.total_sessions,
-            PomodoroState::LongBreak(_) => self.total_sessions,
-        }
+        self.get_phase().session().unwrap_or(self.completed)
     }
 
+    /// The daily target, or 0 when cycling until stopped.
     pub fn get_total_sessions(&self) -> usize {
         self.total_sessions
     }
 
+    pub fn get_long_break_every(&self) -> usize {
+        self.long_break_every
+    }
+
+    /// Where the current session falls within its set, counted from 1.
+    pub fn get_set_position(&self) -> usize {
+        self.get_phase()
+            .set_position(self.long_break_every)
+            .unwrap_or(0)
+    }
+
+    /// Focus sessions run to their end so far today.
+    pub fn get_completed(&self) -> usize {
+        self.completed
+    }
+
     pub fn is_focus(&self) -> bool {
         matches!(self.state, PomodoroState::Focus(_))
     }
@@ -115,10 +615,45 @@ impl Pomodoro {
         self.state == PomodoroState::Completed
     }
 
+    pub fn is_overtime(&self) -> bool {
+        self.timer.as_ref().is_some_and(Timer::is_overtime)
+    }
+
     pub fn get_state(&self) -> PomodoroState {
         self.state
     }
 
+    /// The phase waiting to be started, while at `Ready`.
+    pub fn get_pending(&self) -> Option<PomodoroState> {
+        self.pending
+    }
+
+    /// The running phase, or the one waiting to be started while at `Ready`.
+    pub fn get_phase(&self) -> PomodoroState {
+        match self.state {
+            PomodoroState::Ready => self.pending.unwrap_or(PomodoroState::Focus(1)),
+            state => state,
+        }
+    }
+
+    /// Time spent at `Ready` waiting for phases to be started.
+    pub fn get_waited(&self) -> Duration {
+        let waiting = self.ready_since.map_or(Duration::ZERO, |since| {
+            self.clock.now().saturating_duration_since(since)
+        });
+        self.waited + waiting
+    }
+
+    /// Time phases ran past their end, counting the one in overtime now.
+    pub fn get_overrun(&self) -> Duration {
+        let current = self
+            .timer
+            .as_ref()
+            .filter(|timer| timer.is_overtime())
+            .map_or(Duration::ZERO, Timer::overrun);
+        self.overrun + current
+    }
+
     pub fn set_state(&mut self, state: PomodoroState) {
         self.state = state;
     }
@@ -129,24 +664,15 @@ pub struct PomodoroSession {
 }
 
 impl PomodoroSession {
-    pub fn new(
-        total_sessions: usize,
-        focus_duration: Duration,
-        break_duration: Duration,
-        long_break_duration: Duration,
-    ) -> Self {
-        let first_timer = Timer::new(focus_duration, "Focus".to_string());
-
-        let pomodoro = Pomodoro::new(
-            total_sessions,
-            focus_duration,
-            break_duration,
-            long_break_duration,
-            first_timer,
-        );
-
+    pub fn new(pomodoro: Pomodoro) -> Self {
         PomodoroSession { pomodoro }
     }
+
+    pub fn restore(snapshot: PomodoroSnapshot) -> Self {
+        PomodoroSession {
+            pomodoro: Pomodoro::restore(snapshot, clock::system_clock()),
+        }
+    }
 }
 
 impl Session for PomodoroSession {
@@ -159,8 +685,10 @@ impl Session for PomodoroSession {
     }
 
     fn toggle_pause(&mut self) {
-        if let Some(timer) = &mut self.pomodoro.timer {
-            timer.toggle_pause();
+        if self.pomodoro.state == PomodoroState::Ready {
+            self.pomodoro.start_pending();
+        } else {
+            self.pomodoro.toggle_pause();
         }
     }
 
@@ -171,4 +699,513 @@ impl Session for PomodoroSession {
     fn get_pomodoro(&mut self) -> Option<&mut Pomodoro> {
         Some(&mut self.pomodoro)
     }
+
+    fn snapshot(&self) -> SessionSnapshot {
+        SessionSnapshot::Pomodoro(self.pomodoro.snapshot())
+    }
+
+    fn restore(&mut self, snapshot: SessionSnapshot) -> Result<(), SnapshotMismatch> {
+        match snapshot {
+            SessionSnapshot::Pomodoro(snapshot) => {
+                self.pomodoro = Pomodoro::restore(snapshot, self.pomodoro.clock.clone());
+                Ok(())
+            }
+            _ => Err(SnapshotMismatch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use std::sync::Arc;
+
+    const FOCUS: Duration = Duration::from_secs(25 * 60);
+    const BREAK: Duration = Duration::from_secs(5 * 60);
+    const LONG_BREAK: Duration = Duration::from_secs(15 * 60);
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn manual_pomodoro(total_sessions: usize) -> (Pomodoro, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock::new());
+        let pomodoro = Pomodoro::builder()
+            .sessions(total_sessions)
+            .focus(FOCUS)
+            .short_break(BREAK)
+            .long_break(LONG_BREAK)
+            .clock(clock.clone())
+            .build();
+        (pomodoro, clock)
+    }
+
+    fn run_phase(pomodoro: &mut Pomodoro, clock: &ManualClock, duration: Duration) {
+        clock.advance(duration);
+        pomodoro.tick();
+    }
+
+    #[test]
+    fn test_full_cycle() {
+        let (mut pomodoro, clock) = manual_pomodoro(4);
+
+        for session in 1..=3 {
+            assert_eq!(pomodoro.get_state(), PomodoroState::Focus(session));
+            assert_eq!(pomodoro.get_current_session(), session);
+            assert_eq!(pomodoro.get_set_position(), session);
+            assert_eq!(pomodoro.get_timer().unwrap().get_duration(), FOCUS);
+
+            // Nothing moves before the phase is over
+            run_phase(&mut pomodoro, &clock, FOCUS - Duration::from_secs(1));
+            assert_eq!(pomodoro.get_state(), PomodoroState::Focus(session));
+
+            run_phase(&mut pomodoro, &clock, Duration::from_secs(1));
+            assert_eq!(pomodoro.get_state(), PomodoroState::Break(session));
+            assert_eq!(pomodoro.get_timer().unwrap().get_name(), "Break");
+            assert_eq!(pomodoro.get_completed(), session);
+
+            run_phase(&mut pomodoro, &clock, BREAK);
+        }
+
+        // The last session of the set gets the long break instead of a short one
+        run_phase(&mut pomodoro, &clock, FOCUS);
+        assert_eq!(pomodoro.get_state(), PomodoroState::LongBreak(4));
+        assert_eq!(pomodoro.get_current_session(), 4);
+        assert_eq!(pomodoro.get_timer().unwrap().get_duration(), LONG_BREAK);
+
+        run_phase(&mut pomodoro, &clock, LONG_BREAK);
+        assert!(pomodoro.is_completed());
+        assert!(pomodoro.get_timer().is_none());
+        assert_eq!(pomodoro.get_current_session(), 4);
+        assert_eq!(pomodoro.get_completed(), 4);
+
+        // Ticking a finished cycle is harmless
+        run_phase(&mut pomodoro, &clock, FOCUS);
+        assert!(pomodoro.is_completed());
+    }
+
+    #[test]
+    fn test_one_phase_per_tick() {
+        let (mut pomodoro, clock) = manual_pomodoro(4);
+
+        // A long stall still only finishes the phase that was running
+        run_phase(&mut pomodoro, &clock, 3 * FOCUS);
+        assert_eq!(pomodoro.get_state(), PomodoroState::Break(1));
+        assert_eq!(pomodoro.get_timer().unwrap().elapsed_time(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_pause() {
+        let (mut pomodoro, clock) = manual_pomodoro(4);
+
+        run_phase(&mut pomodoro, &clock, Duration::from_secs(10 * 60));
+        pomodoro.get_timer().unwrap().toggle_pause();
+        run_phase(&mut pomodoro, &clock, 2 * FOCUS);
+        assert_eq!(pomodoro.get_state(), PomodoroState::Focus(1));
+
+        pomodoro.get_timer().unwrap().toggle_pause();
+        run_phase(&mut pomodoro, &clock, FOCUS - Duration::from_secs(10 * 60));
+        assert_eq!(pomodoro.get_state(), PomodoroState::Break(1));
+
+        // A phase paused right as it ends doesn't move on either
+        run_phase(&mut pomodoro, &clock, BREAK - Duration::from_secs(1));
+        pomodoro.get_timer().unwrap().toggle_pause();
+        run_phase(&mut pomodoro, &clock, BREAK);
+        assert_eq!(pomodoro.get_state(), PomodoroState::Break(1));
+    }
+
+    #[test]
+    fn test_quit() {
+        let (mut pomodoro, clock) = manual_pomodoro(4);
+
+        run_phase(&mut pomodoro, &clock, FOCUS);
+        run_phase(&mut pomodoro, &clock, BREAK);
+        assert_eq!(pomodoro.get_state(), PomodoroState::Focus(2));
+
+        pomodoro.get_timer().unwrap().set_status(TimerStatus::Exit);
+        pomodoro.tick();
+        assert!(pomodoro.is_completed());
+
+        // Quitting while paused
+        let (mut pomodoro, _clock) = manual_pomodoro(4);
+        pomodoro.get_timer().unwrap().toggle_pause();
+        pomodoro.get_timer().unwrap().set_status(TimerStatus::Exit);
+        pomodoro.tick();
+        assert!(pomodoro.is_completed());
+    }
+
+    #[test]
+    fn test_target_ends_mid_set() {
+        let (mut pomodoro, clock) = manual_pomodoro(1);
+
+        run_phase(&mut pomodoro, &clock, FOCUS);
+        assert_eq!(pomodoro.get_state(), PomodoroState::Break(1));
+        run_phase(&mut pomodoro, &clock, BREAK);
+        assert!(pomodoro.is_completed());
+    }
+
+    #[test]
+    fn test_continuous() {
+        let clock = Arc::new(ManualClock::new());
+        let mut pomodoro = Pomodoro::builder()
+            .continuous()
+            .long_break_every(2)
+            .focus(FOCUS)
+            .short_break(BREAK)
+            .long_break(LONG_BREAK)
+            .clock(clock.clone())
+            .build();
+
+        for session in 1..=6 {
+            assert_eq!(pomodoro.get_state(), PomodoroState::Focus(session));
+            assert_eq!(pomodoro.get_set_position(), (session - 1) % 2 + 1);
+
+            run_phase(&mut pomodoro, &clock, FOCUS);
+            if session % 2 == 0 {
+                assert_eq!(pomodoro.get_state(), PomodoroState::LongBreak(session));
+                run_phase(&mut pomodoro, &clock, LONG_BREAK);
+            } else {
+                assert_eq!(pomodoro.get_state(), PomodoroState::Break(session));
+                run_phase(&mut pomodoro, &clock, BREAK);
+            }
+        }
+
+        assert_eq!(pomodoro.get_state(), PomodoroState::Focus(7));
+        assert_eq!(pomodoro.get_completed(), 6);
+        assert_eq!(pomodoro.get_total_sessions(), 0);
+        assert_eq!(PomodoroState::LongBreak(6).set_position(2), Some(2));
+        assert_eq!(PomodoroState::Completed.set_position(2), None);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let (mut pomodoro, clock) = manual_pomodoro(4);
+        let minute = Duration::from_secs(60);
+
+        run_phase(&mut pomodoro, &clock, FOCUS);
+        run_phase(&mut pomodoro, &clock, BREAK);
+        pomodoro.extend_focus(minute);
+        run_phase(&mut pomodoro, &clock, minute);
+        pomodoro.get_timer().unwrap().toggle_pause();
+
+        let json = serde_json::to_string(&pomodoro.snapshot()).unwrap();
+        let snapshot: PomodoroSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(snapshot, pomodoro.snapshot());
+
+        let mut restored = Pomodoro::restore(snapshot.clone(), clock.clone());
+        assert_eq!(restored.get_state(), PomodoroState::Focus(2));
+        let timer = restored.get_timer().unwrap();
+        assert!(timer.is_paused());
+        assert_eq!(timer.remaining_time(), FOCUS);
+
+        // A running timer that overran finishes its phase, and the time past it was spent
+        // waiting rather than going through the break and the next focus
+        let mut snapshot = snapshot;
+        let timer = snapshot.timer.as_mut().unwrap();
+        timer.status = TimerStatus::Running;
+        timer.elapsed += FOCUS + BREAK + minute;
+        let mut restored = Pomodoro::restore(snapshot, clock.clone());
+        assert_eq!(restored.get_state(), PomodoroState::Break(2));
+        assert_eq!(restored.get_completed(), 2);
+        assert_eq!(restored.get_waited(), BREAK + minute);
+        assert_eq!(restored.get_timer().unwrap().remaining_time(), BREAK);
+
+        let mut pomodoro = Pomodoro::restore(restored.snapshot(), clock.clone());
+        run_phase(&mut pomodoro, &clock, BREAK);
+        assert_eq!(pomodoro.get_state(), PomodoroState::Focus(3));
+    }
+
+    #[test]
+    fn test_restore_after_long_gap() {
+        let clock = Arc::new(ManualClock::new());
+        let pomodoro = Pomodoro::builder()
+            .continuous()
+            .focus(Duration::from_secs(60))
+            .short_break(Duration::from_secs(60))
+            .clock(clock.clone())
+            .build();
+
+        // Hours down only finish the phase that was running
+        let mut snapshot = pomodoro.snapshot();
+        snapshot.timer.as_mut().unwrap().elapsed = Duration::from_secs(10 * 60 * 60);
+        let restored = Pomodoro::restore(snapshot, clock.clone());
+        assert_eq!(restored.get_state(), PomodoroState::Break(1));
+        assert_eq!(restored.get_completed(), 1);
+
+        // Phases that take no time at all don't keep it catching up forever
+        let pomodoro = Pomodoro::builder()
+            .continuous()
+            .focus(Duration::ZERO)
+            .short_break(Duration::ZERO)
+            .long_break(Duration::ZERO)
+            .clock(clock.clone())
+            .build();
+        let mut snapshot = pomodoro.snapshot();
+        snapshot.timer.as_mut().unwrap().elapsed = Duration::from_secs(60);
+        let restored = Pomodoro::restore(snapshot, clock);
+        assert_eq!(restored.get_state(), PomodoroState::Break(1));
+    }
+
+    #[test]
+    fn test_skip_and_extend() {
+        let (mut pomodoro, clock) = manual_pomodoro(3);
+
+        // Breaks can't be extended and focus can't be skipped as a break
+        pomodoro.skip_break();
+        assert_eq!(pomodoro.get_state(), PomodoroState::Focus(1));
+        pomodoro.extend_focus(BREAK);
+        run_phase(&mut pomodoro, &clock, FOCUS);
+        assert_eq!(pomodoro.get_state(), PomodoroState::Focus(1));
+        run_phase(&mut pomodoro, &clock, BREAK);
+        assert_eq!(pomodoro.get_state(), PomodoroState::Break(1));
+
+        pomodoro.extend_focus(BREAK);
+        assert_eq!(pomodoro.get_timer().unwrap().get_duration(), BREAK);
+        pomodoro.skip_break();
+        assert_eq!(pomodoro.get_state(), PomodoroState::Focus(2));
+        assert_eq!(pomodoro.get_completed(), 1);
+
+        // Starting the next session from focus goes straight past the break, and the skipped
+        // focus doesn't count as completed
+        pomodoro.start_next_session();
+        assert_eq!(pomodoro.get_state(), PomodoroState::Focus(3));
+        assert_eq!(pomodoro.get_completed(), 1);
+
+        pomodoro.skip_phase();
+        assert_eq!(pomodoro.get_state(), PomodoroState::Break(3));
+        pomodoro.skip_phase();
+        assert!(pomodoro.is_completed());
+        pomodoro.skip_phase();
+        assert!(pomodoro.is_completed());
+    }
+
+    #[test]
+    fn test_manual_start() {
+        let clock = Arc::new(ManualClock::new());
+        let mut pomodoro = Pomodoro::builder()
+            .sessions(2)
+            .focus(FOCUS)
+            .short_break(BREAK)
+            .long_break(LONG_BREAK)
+            .auto_start(AutoStart {
+                focus: false,
+                breaks: true,
+            })
+            .clock(clock.clone())
+            .build();
+        let minute = Duration::from_secs(60);
+
+        // Even the first focus waits, and its timer doesn't run meanwhile
+        assert_eq!(pomodoro.get_state(), PomodoroState::Ready);
+        assert_eq!(pomodoro.get_pending(), Some(PomodoroState::Focus(1)));
+        assert_eq!(pomodoro.get_current_session(), 1);
+        run_phase(&mut pomodoro, &clock, 2 * FOCUS);
+        assert_eq!(pomodoro.get_state(), PomodoroState::Ready);
+        assert_eq!(pomodoro.get_waited(), 2 * FOCUS);
+
+        pomodoro.start_pending();
+        assert_eq!(pomodoro.get_state(), PomodoroState::Focus(1));
+        assert_eq!(pomodoro.get_pending(), None);
+
+        // Breaks still start on their own, the focus after them waits
+        run_phase(&mut pomodoro, &clock, FOCUS);
+        assert_eq!(pomodoro.get_state(), PomodoroState::Break(1));
+        run_phase(&mut pomodoro, &clock, BREAK);
+        assert_eq!(pomodoro.get_state(), PomodoroState::Ready);
+        assert_eq!(pomodoro.get_phase(), PomodoroState::Focus(2));
+
+        run_phase(&mut pomodoro, &clock, minute);
+        assert_eq!(pomodoro.get_waited(), 2 * FOCUS + minute);
+
+        // A snapshot keeps waiting, then skipping moves past the waiting phase
+        let mut restored = Pomodoro::restore(pomodoro.snapshot(), clock.clone());
+        assert_eq!(restored.get_state(), PomodoroState::Ready);
+        assert_eq!(restored.get_waited(), 2 * FOCUS + minute);
+        restored.skip_phase();
+        assert_eq!(restored.get_state(), PomodoroState::Break(2));
+        assert_eq!(restored.get_timer().unwrap().elapsed_time(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_overtime() {
+        let clock = Arc::new(ManualClock::new());
+        let mut pomodoro = Pomodoro::builder()
+            .sessions(2)
+            .focus(FOCUS)
+            .short_break(BREAK)
+            .overtime(true)
+            .clock(clock.clone())
+            .build();
+        let minute = Duration::from_secs(60);
+
+        // The focus keeps counting past its end instead of moving on
+        run_phase(&mut pomodoro, &clock, FOCUS + 3 * minute);
+        assert_eq!(pomodoro.get_state(), PomodoroState::Focus(1));
+        assert!(pomodoro.is_overtime());
+        assert_eq!(pomodoro.get_overrun(), 3 * minute);
+        assert_eq!(pomodoro.get_completed(), 0);
+
+        // Restoring doesn't move past it either
+        let restored = Pomodoro::restore(pomodoro.snapshot(), clock.clone());
+        assert_eq!(restored.get_state(), PomodoroState::Focus(1));
+        assert!(restored.is_overtime());
+
+        pomodoro.end_overtime();
+        assert_eq!(pomodoro.get_state(), PomodoroState::Break(1));
+        assert_eq!(pomodoro.get_completed(), 1);
+
+        // Skipping an overrun break dismisses it and keeps the overrun
+        run_phase(&mut pomodoro, &clock, BREAK + minute);
+        pomodoro.skip_phase();
+        assert_eq!(pomodoro.get_state(), PomodoroState::Focus(2));
+        assert_eq!(pomodoro.get_overrun(), 4 * minute);
+        assert_eq!(pomodoro.snapshot().overrun, 4 * minute);
+    }
+
+    #[test]
+    fn test_fit_within() {
+        let fitted = |available: Duration| {
+            let clock = Arc::new(ManualClock::new());
+            let pomodoro = Pomodoro::builder()
+                .focus(FOCUS)
+                .short_break(BREAK)
+                .long_break(LONG_BREAK)
+                .fit_within(available)
+                .clock(clock.clone())
+                .build();
+            (pomodoro, clock)
+        };
+        let minute = Duration::from_secs(60);
+
+        // Three full cycles, then a focus session and a long break cut to five minutes
+        let (mut pomodoro, clock) = fitted(120 * minute);
+        assert_eq!(pomodoro.get_total_sessions(), 4);
+        for _ in 1..=3 {
+            run_phase(&mut pomodoro, &clock, FOCUS);
+            run_phase(&mut pomodoro, &clock, BREAK);
+        }
+        run_phase(&mut pomodoro, &clock, FOCUS);
+        assert_eq!(pomodoro.get_state(), PomodoroState::LongBreak(4));
+        assert_eq!(pomodoro.get_timer().unwrap().get_duration(), 5 * minute);
+        run_phase(&mut pomodoro, &clock, 5 * minute);
+        assert!(pomodoro.is_completed());
+
+        // The last focus session is cut short and there's no time left for its break
+        let (mut pomodoro, clock) = fitted(100 * minute);
+        assert_eq!(pomodoro.get_total_sessions(), 4);
+        for _ in 1..=3 {
+            run_phase(&mut pomodoro, &clock, FOCUS);
+            run_phase(&mut pomodoro, &clock, BREAK);
+        }
+        assert_eq!(pomodoro.get_timer().unwrap().get_duration(), 10 * minute);
+        run_phase(&mut pomodoro, &clock, 10 * minute);
+        assert!(pomodoro.is_completed());
+
+        // Less time than a single session still gets one, cut to fit
+        let (mut pomodoro, _clock) = fitted(10 * minute);
+        assert_eq!(pomodoro.get_total_sessions(), 1);
+        assert_eq!(pomodoro.get_timer().unwrap().get_duration(), 10 * minute);
+
+        // Time spent paused comes off the phase rather than going past the deadline
+        let (mut pomodoro, clock) = fitted(30 * minute);
+        pomodoro.toggle_pause();
+        clock.advance(20 * minute);
+        pomodoro.toggle_pause();
+        assert_eq!(pomodoro.get_timer().unwrap().remaining_time(), 10 * minute);
+
+        // And so does time spent waiting for the phase to be started
+        let clock = Arc::new(ManualClock::new());
+        let mut pomodoro = Pomodoro::builder()
+            .focus(FOCUS)
+            .short_break(BREAK)
+            .auto_start(AutoStart {
+                focus: false,
+                breaks: true,
+            })
+            .fit_within(30 * minute)
+            .clock(clock.clone())
+            .build();
+        clock.advance(20 * minute);
+        pomodoro.start_pending();
+        assert_eq!(pomodoro.get_timer().unwrap().remaining_time(), 10 * minute);
+
+        // Lengths too long to add up don't overflow
+        let pomodoro = Pomodoro::builder()
+            .focus(Duration::MAX)
+            .short_break(Duration::from_secs(1))
+            .fit_within(60 * minute)
+            .build();
+        assert_eq!(pomodoro.get_total_sessions(), 1);
+    }
+
+    #[test]
+    fn test_progressive_lengths() {
+        let clock = Arc::new(ManualClock::new());
+        let mut pomodoro = Pomodoro::builder()
+            .sessions(0)
+            .long_break_every(0)
+            .focus(Lengths::Ramp {
+                start: 15 * MINUTE,
+                step: 5 * MINUTE,
+                max: Some(25 * MINUTE),
+            })
+            .short_break(Lengths::List(vec![3 * MINUTE, 4 * MINUTE]))
+            .clock(clock.clone())
+            .build();
+
+        let expected = [(15, 3), (20, 4), (25, 4), (25, 4)];
+        for (session, (focus, rest)) in (1..).zip(expected) {
+            assert_eq!(pomodoro.get_timer().unwrap().get_duration(), focus * MINUTE);
+            assert_eq!(
+                pomodoro.get_next_phase(),
+                Some((PomodoroState::Break(session), rest * MINUTE))
+            );
+            run_phase(&mut pomodoro, &clock, focus * MINUTE);
+            assert_eq!(pomodoro.get_timer().unwrap().get_duration(), rest * MINUTE);
+            run_phase(&mut pomodoro, &clock, rest * MINUTE);
+        }
+    }
+
+    #[test]
+    fn test_progressive_lengths_fit_within() {
+        // Growing sessions fit fewer of them: 18 + 24 + 29 minutes start within an hour
+        let fitted = Pomodoro::builder()
+            .focus(Lengths::Ramp {
+                start: 15 * MINUTE,
+                step: 5 * MINUTE,
+                max: None,
+            })
+            .short_break(Lengths::List(vec![3 * MINUTE, 4 * MINUTE]))
+            .fit_within(60 * MINUTE)
+            .clock(Arc::new(ManualClock::new()))
+            .build();
+        assert_eq!(fitted.get_total_sessions(), 3);
+
+        // A step too long to add up ends the count instead of overflowing it
+        let fitted = Pomodoro::builder()
+            .focus(Lengths::Ramp {
+                start: Duration::from_secs(1),
+                step: Duration::MAX,
+                max: None,
+            })
+            .fit_within(60 * MINUTE)
+            .clock(Arc::new(ManualClock::new()))
+            .build();
+        assert_eq!(fitted.get_total_sessions(), 2);
+    }
+
+    #[test]
+    fn test_single_length_checkpoints_load() {
+        let pomodoro = Pomodoro::builder()
+            .short_break(Lengths::List(vec![3 * MINUTE, 4 * MINUTE]))
+            .clock(Arc::new(ManualClock::new()))
+            .build();
+
+        let mut snapshot = serde_json::to_value(pomodoro.snapshot()).unwrap();
+        snapshot["focus_duration"] = serde_json::json!({ "secs": 1500, "nanos": 0 });
+        let snapshot: PomodoroSnapshot = serde_json::from_value(snapshot).unwrap();
+        assert_eq!(snapshot.focus_duration, Lengths::Fixed(FOCUS));
+        assert_eq!(
+            snapshot.break_duration,
+            Lengths::List(vec![3 * MINUTE, 4 * MINUTE])
+        );
+    }
 }
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::flowtime::{Flowtime, FlowtimeSession, FlowtimeSnapshot};
+use crate::mob::{Mob, MobSession, MobSnapshot};
+use crate::plan::{Plan, PlanSession, PlanSnapshot};
+use crate::pomodoro::{Pomodoro, PomodoroSession, PomodoroSnapshot, PomodoroState};
+use crate::standup::{Standup, StandupSession, StandupSnapshot};
+use crate::stopwatch::{Stopwatch, StopwatchSession, StopwatchSnapshot};
+use crate::timer::{Timer, TimerSession, TimerSnapshot};
+
+/// What the host pushes to peers and browser clients every second.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SessionStatus {
+    pub remaining_secs: u64,
+    pub duration_secs: u64,
+    pub paused: bool,
+    pub phase: Option<PomodoroState>,
+    pub current_session: usize,
+    pub total_sessions: usize,
+}
+
+/// Serializable state of any `Session`, for saving it, sending it to a peer or inspecting it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SessionSnapshot {
+    Timer(TimerSnapshot),
+    Pomodoro(PomodoroSnapshot),
+    Mob(MobSnapshot),
+    Standup(StandupSnapshot),
+    Stopwatch(StopwatchSnapshot),
+    Plan(PlanSnapshot),
+    Flowtime(FlowtimeSnapshot),
+}
+
+#[derive(Error, Debug, PartialEq)]
+#[error("Snapshot is of a different kind of session")]
+pub struct SnapshotMismatch;
+
+impl SessionSnapshot {
+    pub fn into_session(self) -> Box<dyn Session> {
+        match self {
+            SessionSnapshot::Timer(snapshot) => Box::new(TimerSession::restore(snapshot)),
+            SessionSnapshot::Pomodoro(snapshot) => Box::new(PomodoroSession::restore(snapshot)),
+            SessionSnapshot::Mob(snapshot) => Box::new(MobSession::restore(snapshot)),
+            SessionSnapshot::Standup(snapshot) => Box::new(StandupSession::restore(snapshot)),
+            SessionSnapshot::Stopwatch(snapshot) => Box::new(StopwatchSession::restore(snapshot)),
+            SessionSnapshot::Plan(snapshot) => Box::new(PlanSession::restore(snapshot)),
+            SessionSnapshot::Flowtime(snapshot) => Box::new(FlowtimeSession::restore(snapshot)),
+        }
+    }
+
+    pub fn get_timer(&self) -> Option<&TimerSnapshot> {
+        match self {
+            SessionSnapshot::Timer(timer) => Some(timer),
+            SessionSnapshot::Pomodoro(pomodoro) => pomodoro.timer.as_ref(),
+            SessionSnapshot::Mob(mob) => mob.timer.as_ref(),
+            SessionSnapshot::Standup(standup) => Some(&standup.timer),
+            SessionSnapshot::Stopwatch(stopwatch) => Some(&stopwatch.timer),
+            SessionSnapshot::Plan(plan) => Some(&plan.timer),
+            SessionSnapshot::Flowtime(flowtime) => Some(&flowtime.timer),
+        }
+    }
+
+    pub fn get_timer_mut(&mut self) -> Option<&mut TimerSnapshot> {
+        match self {
+            SessionSnapshot::Timer(timer) => Some(timer),
+            SessionSnapshot::Pomodoro(pomodoro) => pomodoro.timer.as_mut(),
+            SessionSnapshot::Mob(mob) => mob.timer.as_mut(),
+            SessionSnapshot::Standup(standup) => Some(&mut standup.timer),
+            SessionSnapshot::Stopwatch(stopwatch) => Some(&mut stopwatch.timer),
+            SessionSnapshot::Plan(plan) => Some(&mut plan.timer),
+            SessionSnapshot::Flowtime(flowtime) => Some(&mut flowtime.timer),
+        }
+    }
+}
+
+pub trait Session: Send {
+    fn tick(&mut self);
+    fn is_finished(&self) -> bool;
+    fn toggle_pause(&mut self);
+    fn get_timer(&mut self) -> Option<&mut Timer>;
+    // A session runs one mode, so it only overrides the accessor for its own
+    fn get_pomodoro(&mut self) -> Option<&mut Pomodoro> {
+        None
+    }
+    fn get_mob(&mut self) -> Option<&mut Mob> {
+        None
+    }
+    fn get_standup(&mut self) -> Option<&mut Standup> {
+        None
+    }
+    fn get_stopwatch(&mut self) -> Option<&mut Stopwatch> {
+        None
+    }
+    fn get_plan(&mut self) -> Option<&mut Plan> {
+        None
+    }
+    fn get_flowtime(&mut self) -> Option<&mut Flowtime> {
+        None
+    }
+    fn snapshot(&self) -> SessionSnapshot;
+    fn restore(&mut self, snapshot: SessionSnapshot) -> Result<(), SnapshotMismatch>;
+}
@@ -0,0 +1,374 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::clock::{self, SharedClock};
+use crate::parser::{parse_duration, ParseTimeError};
+use crate::session::{Session, SessionSnapshot, SnapshotMismatch};
+use crate::timer::{Timer, TimerSnapshot, TimerStatus};
+
+/// A speaker as given on the command line, `name` or `name:timebox`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Speaker {
+    pub name: String,
+    pub timebox: Option<Duration>,
+}
+
+impl FromStr for Speaker {
+    type Err = ParseTimeError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.split_once(':') {
+            Some((name, timebox)) => Ok(Speaker {
+                name: name.trim().to_string(),
+                timebox: Some(
+                    parse_duration(timebox).map_err(|error| error.offset(name.len() + 1))?,
+                ),
+            }),
+            None => Ok(Speaker {
+                name: input.trim().to_string(),
+                timebox: None,
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpeakerRecord {
+    pub name: String,
+    pub timebox: Duration,
+    pub spoken: Duration,
+}
+
+impl SpeakerRecord {
+    pub fn overrun(&self) -> Duration {
+        self.spoken.saturating_sub(self.timebox)
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum StandupState {
+    Speaking(usize),
+    Completed,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StandupSnapshot {
+    pub state: StandupState,
+    pub speakers: Vec<(String, Duration)>,
+    pub records: Vec<SpeakerRecord>,
+    pub timer: TimerSnapshot,
+}
+
+pub struct Standup {
+    state: StandupState,
+    speakers: Vec<(String, Duration)>,
+    records: Vec<SpeakerRecord>,
+    timer: Timer,
+}
+
+impl Standup {
+    pub fn new(speakers: Vec<Speaker>, default_timebox: Duration, clock: SharedClock) -> Self {
+        let speakers: Vec<(String, Duration)> = speakers
+            .into_iter()
+            .map(|speaker| (speaker.name, speaker.timebox.unwrap_or(default_timebox)))
+            .collect();
+
+        let (name, timebox) = speakers
+            .first()
+            .cloned()
+            .unwrap_or_else(|| ("Nobody".to_string(), default_timebox));
+
+        Standup {
+            state: StandupState::Speaking(0),
+            speakers,
+            records: Vec::new(),
+            timer: Timer::with_clock(timebox, name, clock),
+        }
+    }
+
+    pub fn snapshot(&self) -> StandupSnapshot {
+        StandupSnapshot {
+            state: self.state,
+            speakers: self.speakers.clone(),
+            records: self.records.clone(),
+            timer: self.timer.snapshot(),
+        }
+    }
+
+    pub fn restore(snapshot: StandupSnapshot, clock: SharedClock) -> Self {
+        Standup {
+            state: snapshot.state,
+            speakers: snapshot.speakers,
+            records: snapshot.records,
+            timer: Timer::restore(snapshot.timer, clock),
+        }
+    }
+
+    pub fn tick(&mut self) {
+        // Speakers are advanced by the facilitator, the timer keeps counting past the timebox
+        if self.timer.get_status() == TimerStatus::Exit {
+            self.finish();
+        }
+    }
+
+    pub fn next_speaker(&mut self) {
+        let StandupState::Speaking(index) = self.state else {
+            return;
+        };
+
+        self.record_current();
+
+        match self.speakers.get(index + 1) {
+            Some((name, timebox)) => {
+                self.timer = Timer::with_clock(*timebox, name.clone(), self.timer.get_clock());
+                self.state = StandupState::Speaking(index + 1);
+            }
+            None => self.state = StandupState::Completed,
+        }
+    }
+
+    pub fn finish(&mut self) {
+        if let StandupState::Speaking(_) = self.state {
+            self.record_current();
+            self.state = StandupState::Completed;
+        }
+    }
+
+    fn record_current(&mut self) {
+        self.records.push(SpeakerRecord {
+            name: self.timer.get_name().to_string(),
+            timebox: self.timer.get_duration(),
+            spoken: self.timer.elapsed_time(),
+        });
+    }
+
+    pub fn get_speaker_index(&self) -> usize {
+        match self.state {
+            StandupState::Speaking(index) => index,
+            StandupState::Completed => self.speakers.len(),
+        }
+    }
+
+    pub fn get_total_speakers(&self) -> usize {
+        self.speakers.len()
+    }
+
+    pub fn get_next_speaker(&self) -> Option<&str> {
+        self.speakers
+            .get(self.get_speaker_index() + 1)
+            .map(|(name, _)| name.as_str())
+    }
+
+    pub fn get_overrun(&self) -> Duration {
+        self.timer
+            .elapsed_time()
+            .saturating_sub(self.timer.get_duration())
+    }
+
+    pub fn get_records(&self) -> &[SpeakerRecord] {
+        &self.records
+    }
+
+    pub fn get_timer(&mut self) -> Option<&mut Timer> {
+        match self.state {
+            StandupState::Speaking(_) => Some(&mut self.timer),
+            StandupState::Completed => None,
+        }
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.state == StandupState::Completed
+    }
+
+    pub fn summary(&self) -> StandupSummary<'_> {
+        StandupSummary {
+            records: self.get_records(),
+            timer: &self.timer,
+        }
+    }
+}
+
+pub struct StandupSummary<'a> {
+    records: &'a [SpeakerRecord],
+    timer: &'a Timer,
+}
+
+impl fmt::Display for StandupSummary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Standup summary")?;
+
+        for record in self.records {
+            let overrun = record.overrun();
+            if overrun.is_zero() {
+                writeln!(
+                    f,
+                    "  {}: {} of {}",
+                    record.name,
+                    self.timer.format_duration(record.spoken),
+                    self.timer.format_duration(record.timebox)
+                )?;
+            } else {
+                writeln!(
+                    f,
+                    "  {}: {} of {}, over by {}",
+                    record.name,
+                    self.timer.format_duration(record.spoken),
+                    self.timer.format_duration(record.timebox),
+                    self.timer.format_duration(overrun)
+                )?;
+            }
+        }
+
+        let total: Duration = self.records.iter().map(|record| record.spoken).sum();
+        write!(f, "Total: {}", self.timer.format_duration(total))
+    }
+}
+
+pub struct StandupSession {
+    standup: Standup,
+}
+
+impl StandupSession {
+    pub fn new(speakers: Vec<Speaker>, default_timebox: Duration) -> Self {
+        StandupSession {
+            standup: Standup::new(speakers, default_timebox, clock::system_clock()),
+        }
+    }
+
+    pub fn restore(snapshot: StandupSnapshot) -> Self {
+        StandupSession {
+            standup: Standup::restore(snapshot, clock::system_clock()),
+        }
+    }
+}
+
+impl Session for StandupSession {
+    fn tick(&mut self) {
+        self.standup.tick()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.standup.is_completed()
+    }
+
+    fn toggle_pause(&mut self) {
+        if let Some(timer) = self.standup.get_timer() {
+            timer.toggle_pause();
+        }
+    }
+
+    fn get_timer(&mut self) -> Option<&mut Timer> {
+        self.standup.get_timer()
+    }
+
+    fn get_standup(&mut self) -> Option<&mut Standup> {
+        Some(&mut self.standup)
+    }
+
+    fn snapshot(&self) -> SessionSnapshot {
+        SessionSnapshot::Standup(self.standup.snapshot())
+    }
+
+    fn restore(&mut self, snapshot: SessionSnapshot) -> Result<(), SnapshotMismatch> {
+        match snapshot {
+            SessionSnapshot::Standup(snapshot) => {
+                let clock = self.standup.timer.get_clock();
+                self.standup = Standup::restore(snapshot, clock);
+                Ok(())
+            }
+            _ => Err(SnapshotMismatch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_parse_speaker() {
+        assert_eq!(
+            "alice:2m".parse::<Speaker>().unwrap(),
+            Speaker {
+                name: "alice".to_string(),
+                timebox: Some(Duration::from_secs(120)),
+            }
+        );
+        assert_eq!(
+            "bob".parse::<Speaker>().unwrap(),
+            Speaker {
+                name: "bob".to_string(),
+                timebox: None,
+            }
+        );
+        assert!("carol:2x".parse::<Speaker>().is_err());
+    }
+
+    #[test]
+    fn test_speakers_and_overrun() {
+        let clock = Arc::new(ManualClock::new());
+        let speakers = vec!["alice:1m".parse().unwrap(), "bob".parse().unwrap()];
+        let mut standup = Standup::new(speakers, Duration::from_secs(120), clock.clone());
+
+        assert_eq!(standup.get_next_speaker(), Some("bob"));
+        clock.advance(Duration::from_secs(90));
+        standup.tick();
+        assert_eq!(standup.get_speaker_index(), 0);
+        assert_eq!(standup.get_overrun(), Duration::from_secs(30));
+
+        standup.next_speaker();
+        assert_eq!(standup.get_timer().unwrap().get_name(), "bob");
+        assert_eq!(standup.get_next_speaker(), None);
+
+        clock.advance(Duration::from_secs(100));
+        standup.next_speaker();
+        assert!(standup.is_completed());
+
+        let records = standup.get_records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].spoken, Duration::from_secs(90));
+        assert_eq!(records[0].overrun(), Duration::from_secs(30));
+        assert_eq!(records[1].timebox, Duration::from_secs(120));
+        assert_eq!(records[1].spoken, Duration::from_secs(100));
+        assert!(records[1].overrun().is_zero());
+    }
+
+    #[test]
+    fn test_quit_records_current_speaker() {
+        let speakers = vec!["alice".parse().unwrap(), "bob".parse().unwrap()];
+        let mut standup = Standup::new(speakers, Duration::from_secs(60), clock::system_clock());
+
+        standup.get_timer().unwrap().set_status(TimerStatus::Exit);
+        standup.tick();
+
+        assert!(standup.is_completed());
+        assert_eq!(standup.get_records().len(), 1);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let speakers = vec!["alice".parse().unwrap(), "bob:30s".parse().unwrap()];
+        let mut session = StandupSession::new(speakers, Duration::from_secs(60));
+        session.get_standup().unwrap().next_speaker();
+
+        let json = serde_json::to_string(&session.snapshot()).unwrap();
+        let snapshot: SessionSnapshot = serde_json::from_str(&json).unwrap();
+
+        let mut restored = snapshot.clone().into_session();
+        let standup = restored.get_standup().unwrap();
+        assert_eq!(standup.get_speaker_index(), 1);
+        assert_eq!(standup.get_records()[0].name, "alice");
+        assert_eq!(
+            standup.get_timer().unwrap().get_duration(),
+            Duration::from_secs(30)
+        );
+
+        let mut timer = crate::timer::TimerSession::new(Duration::from_secs(60), "Tea".to_string());
+        assert_eq!(timer.restore(snapshot.clone()), Err(SnapshotMismatch));
+        assert_eq!(session.restore(snapshot), Ok(()));
+    }
+}
@@ -0,0 +1,309 @@
+use crate::paths;
+use crate::pomodoro::{Pomodoro, PomodoroState};
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+const STATS_DIR: &str = "stats";
+
+/// One focus phase of a shared pomodoro, as seen by the host.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FocusRecord {
+    pub session: usize,
+    pub started_at: u64,
+    pub ended_at: u64,
+    pub focused: Duration,
+    pub completed: bool,
+    pub participants: BTreeSet<String>,
+}
+
+/// The room's file in the stats directory under the state directory. Anything but letters,
+/// digits, `-` and `_` is percent-encoded, so a room name can't reach outside the directory or
+/// collide with another.
+pub fn room_path(room: &str) -> PathBuf {
+    let mut name = String::new();
+    for byte in room.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' => name.push(byte as char),
+            _ => name.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    paths::state_dir()
+        .join(STATS_DIR)
+        .join(format!("{}.jsonl", name))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Follows the host's pomodoro tick by tick and appends a `FocusRecord` to the room's file
+/// whenever a focus phase ends.
+pub struct StatsRecorder {
+    path: PathBuf,
+    current: Option<FocusRecord>,
+    /// The pomodoro's completed count when the current focus phase began
+    completed_before: usize,
+}
+
+impl StatsRecorder {
+    pub fn new(room: &str) -> Self {
+        StatsRecorder {
+            path: room_path(room),
+            current: None,
+            completed_before: 0,
+        }
+    }
+
+    pub fn observe(&mut self, pomodoro: &mut Pomodoro, participants: Vec<String>) {
+        let state = pomodoro.get_state();
+
+        if let Some(record) = &self.current {
+            if state != PomodoroState::Focus(record.session) {
+                // A focus phase counts as completed when it ran to its end, not when it was
+                // skipped or the pomodoro was quit halfway through
+                let completed = pomodoro.get_completed() > self.completed_before;
+                self.close(completed);
+            }
+        }
+
+        if let PomodoroState::Focus(session) = state {
+            if self.current.is_none() {
+                self.completed_before = pomodoro.get_completed();
+            }
+            let record = self.current.get_or_insert_with(|| FocusRecord {
+                session,
+                started_at: unix_now(),
+                ended_at: 0,
+                focused: Duration::ZERO,
+                completed: false,
+                participants: BTreeSet::new(),
+            });
+
+            record.participants.extend(participants);
+            if let Some(timer) = pomodoro.get_timer() {
+                record.focused = timer.elapsed_time();
+            }
+        }
+    }
+
+    fn close(&mut self, completed: bool) {
+        let Some(mut record) = self.current.take() else {
+            return;
+        };
+
+        record.ended_at = unix_now();
+        record.completed = completed;
+
+        if let Err(e) = self.append(&record) {
+            debug!("STATS: Failed to write record to {:?}: {:?}", self.path, e);
+        }
+    }
+
+    fn append(&self, record: &FocusRecord) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+
+        Ok(())
+    }
+}
+
+pub fn load_records(room: &str) -> io::Result<Vec<FocusRecord>> {
+    let file = File::open(room_path(room))?;
+
+    BufReader::new(file)
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MemberStats {
+    pub attended: usize,
+    pub completed: usize,
+    pub focused: Duration,
+}
+
+pub struct RoomReport {
+    room: String,
+    focus_phases: usize,
+    team_focused: Duration,
+    members: BTreeMap<String, MemberStats>,
+}
+
+impl RoomReport {
+    pub fn new(room: &str, records: &[FocusRecord]) -> Self {
+        let mut members: BTreeMap<String, MemberStats> = BTreeMap::new();
+
+        for record in records {
+            for participant in &record.participants {
+                let member = members.entry(participant.clone()).or_default();
+                member.attended += 1;
+                member.completed += usize::from(record.completed);
+                member.focused += record.focused;
+            }
+        }
+
+        RoomReport {
+            room: room.to_string(),
+            focus_phases: records.len(),
+            team_focused: members.values().map(|member| member.focused).sum(),
+            members,
+        }
+    }
+}
+
+fn hours(duration: Duration) -> f64 {
+    duration.as_secs_f64() / 3600.0
+}
+
+fn percent(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        return 0.0;
+    }
+    part as f64 * 100.0 / whole as f64
+}
+
+impl fmt::Display for RoomReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Room: {}", self.room)?;
+        writeln!(f, "Focus phases: {}", self.focus_phases)?;
+        writeln!(f, "Team focus hours: {:.2}", hours(self.team_focused))?;
+        writeln!(f)?;
+        write!(
+            f,
+            "{:<20} {:>8} {:>11} {:>11}",
+            "Member", "Hours", "Attendance", "Completion"
+        )?;
+
+        for (name, member) in &self.members {
+            write!(
+                f,
+                "\n{:<20} {:>8.2} {:>10.0}% {:>10.0}%",
+                name,
+                hours(member.focused),
+                percent(member.attended, self.focus_phases),
+                percent(member.completed, member.attended)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(completed: bool, participants: &[&str]) -> FocusRecord {
+        FocusRecord {
+            session: 1,
+            started_at: 0,
+            ended_at: 1500,
+            focused: Duration::from_secs(1500),
+            completed,
+            participants: participants.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_room_report() {
+        let records = [
+            record(true, &["alice", "bob"]),
+            record(false, &["alice"]),
+            record(true, &["alice", "bob"]),
+            record(true, &["alice"]),
+        ];
+        let report = RoomReport::new("team", &records);
+
+        assert_eq!(report.focus_phases, 4);
+        assert_eq!(report.team_focused, Duration::from_secs(1500 * 6));
+        assert_eq!(
+            report.members["alice"],
+            MemberStats {
+                attended: 4,
+                completed: 3,
+                focused: Duration::from_secs(1500 * 4),
+            }
+        );
+        assert_eq!(report.members["bob"].attended, 2);
+        assert_eq!(report.members["bob"].completed, 2);
+    }
+
+    #[test]
+    fn test_room_path_stays_in_stats_dir() {
+        let dir = paths::state_dir().join("stats");
+        assert_eq!(room_path("team-1"), dir.join("team-1.jsonl"));
+        assert_eq!(room_path("../../x"), dir.join("%2E%2E%2F%2E%2E%2Fx.jsonl"));
+        assert_ne!(room_path("a b"), room_path("a_b"));
+    }
+
+    #[test]
+    fn test_recorder_tracks_focus_phase() {
+        let mut pomodoro = Pomodoro::new(
+            2,
+            Duration::ZERO,
+            Duration::from_secs(60),
+            Duration::from_secs(60),
+            crate::timer::Timer::new(Duration::ZERO, "Focus".to_string()),
+        );
+        // A directory of its own, so parallel runs don't share the file
+        let dir = std::env::temp_dir().join(format!(
+            "pomoduro-stats-{}-{}",
+            std::process::id(),
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos()
+        ));
+        let mut recorder = StatsRecorder {
+            path: dir.join("room.jsonl"),
+            current: None,
+            completed_before: 0,
+        };
+
+        recorder.observe(&mut pomodoro, vec!["alice".to_string()]);
+        recorder.observe(&mut pomodoro, vec!["bob".to_string()]);
+        assert_eq!(recorder.current.as_ref().unwrap().participants.len(), 2);
+
+        pomodoro.tick();
+        recorder.observe(&mut pomodoro, vec!["alice".to_string()]);
+        assert!(recorder.current.is_none());
+
+        // A skipped focus phase is recorded but doesn't count as completed
+        pomodoro.skip_phase();
+        recorder.observe(&mut pomodoro, vec!["alice".to_string()]);
+        assert!(recorder.current.is_some());
+        pomodoro.skip_phase();
+        recorder.observe(&mut pomodoro, vec!["alice".to_string()]);
+
+        let written = fs::read_to_string(&recorder.path).unwrap();
+        let records: Vec<FocusRecord> = written
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(records[0].completed);
+        assert_eq!(records[0].participants.len(), 2);
+        assert_eq!(records[1].session, 2);
+        assert!(!records[1].completed);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::clock::{self, SharedClock};
+use crate::session::{Session, SessionSnapshot, SnapshotMismatch};
+use crate::timer::{Timer, TimerSnapshot, TimerStatus};
+
+/// One lap: its own time and the stopwatch reading when it was marked.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Lap {
+    pub number: usize,
+    pub split: Duration,
+    pub total: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StopwatchSnapshot {
+    pub timer: TimerSnapshot,
+    pub laps: Vec<Lap>,
+}
+
+/// Counts up until stopped. It runs on a `Timer` that never runs out, so pausing and suspend
+/// handling work as they do for timers.
+pub struct Stopwatch {
+    timer: Timer,
+    laps: Vec<Lap>,
+}
+
+impl Stopwatch {
+    pub fn new(clock: SharedClock) -> Self {
+        Stopwatch {
+            timer: Timer::with_clock(Duration::MAX, "Stopwatch".to_string(), clock),
+            laps: Vec::new(),
+        }
+    }
+
+    pub fn snapshot(&self) -> StopwatchSnapshot {
+        StopwatchSnapshot {
+            timer: self.timer.snapshot(),
+            laps: self.laps.clone(),
+        }
+    }
+
+    pub fn restore(snapshot: StopwatchSnapshot, clock: SharedClock) -> Self {
+        Stopwatch {
+            timer: Timer::restore(snapshot.timer, clock),
+            laps: snapshot.laps,
+        }
+    }
+
+    pub fn lap(&mut self) -> Lap {
+        let total = self.timer.elapsed_time();
+        let previous = self.laps.last().map_or(Duration::ZERO, |lap| lap.total);
+        let lap = Lap {
+            number: self.laps.len() + 1,
+            split: total.saturating_sub(previous),
+            total,
+        };
+
+        self.laps.push(lap);
+        lap
+    }
+
+    /// Starts over from zero and forgets the laps.
+    pub fn reset(&mut self) {
+        self.timer.restart();
+        self.laps.clear();
+    }
+
+    pub fn elapsed_time(&self) -> Duration {
+        self.timer.elapsed_time()
+    }
+
+    pub fn get_laps(&self) -> &[Lap] {
+        &self.laps
+    }
+
+    pub fn get_timer(&mut self) -> &mut Timer {
+        &mut self.timer
+    }
+
+    pub fn laps_csv(&self) -> String {
+        let mut csv = String::from("lap,split_secs,total_secs\n");
+        for lap in &self.laps {
+            let _ = writeln!(
+                csv,
+                "{},{:.3},{:.3}",
+                lap.number,
+                lap.split.as_secs_f64(),
+                lap.total.as_secs_f64()
+            );
+        }
+        csv
+    }
+
+    /// Writes the laps to `path`, as JSON if it ends in `.json` and as CSV otherwise.
+    pub fn export(&self, path: &Path) -> io::Result<()> {
+        let contents = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => serde_json::to_string_pretty(&self.laps)?,
+            _ => self.laps_csv(),
+        };
+        fs::write(path, contents)
+    }
+}
+
+pub struct StopwatchSession {
+    stopwatch: Stopwatch,
+}
+
+impl StopwatchSession {
+    pub fn new() -> Self {
+        StopwatchSession {
+            stopwatch: Stopwatch::new(clock::system_clock()),
+        }
+    }
+
+    pub fn restore(snapshot: StopwatchSnapshot) -> Self {
+        StopwatchSession {
+            stopwatch: Stopwatch::restore(snapshot, clock::system_clock()),
+        }
+    }
+}
+
+impl Default for StopwatchSession {
+    fn default() -> Self {
+        StopwatchSession::new()
+    }
+}
+
+impl Session for StopwatchSession {
+    fn tick(&mut self) {
+        self.stopwatch.timer.tick();
+    }
+
+    fn is_finished(&self) -> bool {
+        self.stopwatch.timer.get_status() == TimerStatus::Exit
+    }
+
+    fn toggle_pause(&mut self) {
+        self.stopwatch.timer.toggle_pause();
+    }
+
+    fn get_timer(&mut self) -> Option<&mut Timer> {
+        Some(self.stopwatch.get_timer())
+    }
+
+    fn get_stopwatch(&mut self) -> Option<&mut Stopwatch> {
+        Some(&mut self.stopwatch)
+    }
+
+    fn snapshot(&self) -> SessionSnapshot {
+        SessionSnapshot::Stopwatch(self.stopwatch.snapshot())
+    }
+
+    fn restore(&mut self, snapshot: SessionSnapshot) -> Result<(), SnapshotMismatch> {
+        match snapshot {
+            SessionSnapshot::Stopwatch(snapshot) => {
+                let clock = self.stopwatch.timer.get_clock();
+                self.stopwatch = Stopwatch::restore(snapshot, clock);
+                Ok(())
+            }
+            _ => Err(SnapshotMismatch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use std::sync::Arc;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn test_laps() {
+        let clock = Arc::new(ManualClock::new());
+        let mut stopwatch = Stopwatch::new(clock.clone());
+
+        clock.advance(2 * MINUTE);
+        assert_eq!(stopwatch.lap().split, 2 * MINUTE);
+
+        // Paused time belongs to no lap
+        stopwatch.get_timer().toggle_pause();
+        clock.advance(10 * MINUTE);
+        stopwatch.get_timer().toggle_pause();
+        clock.advance(3 * MINUTE);
+        let lap = stopwatch.lap();
+        assert_eq!(lap.number, 2);
+        assert_eq!(lap.split, 3 * MINUTE);
+        assert_eq!(lap.total, 5 * MINUTE);
+
+        // A stopwatch never runs out, even days in
+        clock.advance(100 * 24 * 60 * MINUTE);
+        stopwatch.get_timer().tick();
+        assert_eq!(stopwatch.get_timer().get_status(), TimerStatus::Running);
+        assert!(stopwatch.get_timer().deadline().is_none());
+
+        assert_eq!(
+            stopwatch.laps_csv(),
+            "lap,split_secs,total_secs\n1,120.000,120.000\n2,180.000,300.000\n"
+        );
+
+        let json = serde_json::to_string(&stopwatch.snapshot()).unwrap();
+        let snapshot: StopwatchSnapshot = serde_json::from_str(&json).unwrap();
+        let restored = Stopwatch::restore(snapshot, clock.clone());
+        assert_eq!(restored.get_laps(), stopwatch.get_laps());
+        assert_eq!(restored.elapsed_time(), stopwatch.elapsed_time());
+
+        stopwatch.reset();
+        assert!(stopwatch.get_laps().is_empty());
+        assert_eq!(stopwatch.elapsed_time(), Duration::ZERO);
+    }
+}
@@ -1,14 +1,19 @@
-use crate::app::Session;
-use crate::pomodoro::Pomodoro;
+use crate::clock::{self, SharedClock};
+use crate::session::{Session, SessionSnapshot, SessionStatus, SnapshotMismatch};
+use crate::vote::Proposal;
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use std::fmt;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 const SECONDS_PER_MINUTE: u64 = 60;
 const SECONDS_PER_HOUR: u64 = 60 * SECONDS_PER_MINUTE;
 const SECONDS_PER_DAY: u64 = 24 * SECONDS_PER_HOUR;
 
-#[derive(PartialEq, Copy, Clone)]
+// Wall-clock time running ahead of the monotonic clock by more than this is treated as a suspend
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(10);
+
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize, Debug)]
 pub enum TimerStatus {
     Running,
     Paused,
@@ -20,30 +25,159 @@ pub enum TimerStatus {
 pub enum TimerAction {
     Pause,
     Quit,
+    Skip,
+    Restart,
+    Extend(Duration),
+    Lap,
+    Next,
+    Propose(Proposal),
+    Vote(bool),
+    Resolve(bool),
+    Sync(SessionStatus),
+}
+
+/// What to do with the time the machine spent suspended while a timer was running
+#[derive(Clone, Copy, PartialEq, Default, Debug, ValueEnum)]
+pub enum SuspendPolicy {
+    /// Count the time away as elapsed, as if the timer had kept running
+    #[default]
+    Count,
+    /// Pause the timer where it was when the machine went to sleep
+    Pause,
+    /// Pause the timer and ask whether to count the time away
+    Ask,
+}
+
+/// Serializable state of a `Timer`. `deadline` is informational, `elapsed` is what gets restored.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimerSnapshot {
+    pub name: String,
+    pub duration: Duration,
+    pub elapsed: Duration,
+    pub status: TimerStatus,
+    /// Wall-clock finish time in seconds since the Unix epoch, while running
+    pub deadline: Option<u64>,
+    #[serde(default)]
+    pub overtime: bool,
+}
+
+impl TimerSnapshot {
+    /// Whether the timer counts up, see `Timer::counts_up`.
+    pub fn counts_up(&self) -> bool {
+        counts_up(self.duration)
+    }
+}
+
+// Count-up timers are ones that never run out
+fn counts_up(duration: Duration) -> bool {
+    duration == Duration::MAX
+}
+
+/// Builds a `Timer`, e.g. `Timer::builder(Duration::from_secs(300)).name("Tea").build()`.
+pub struct TimerBuilder {
+    duration: Duration,
+    name: String,
+    overtime: bool,
+    clock: SharedClock,
+}
+
+impl TimerBuilder {
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Keeps counting past zero until dismissed instead of finishing.
+    pub fn overtime(mut self, overtime: bool) -> Self {
+        self.overtime = overtime;
+        self
+    }
+
+    pub fn clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn build(self) -> Timer {
+        let mut timer = Timer::with_clock(self.duration, self.name, self.clock);
+        timer.overtime = self.overtime;
+        timer
+    }
 }
 
 #[derive(Clone)]
 pub struct Timer {
     status: TimerStatus,
     started_at: Instant,
+    // Wall-clock reading taken together with `started_at`; Instant stops while suspended, this doesn't
+    wall_started_at: SystemTime,
     elapsed: Duration,
     duration: Duration,
     name: String,
+    /// Whether the timer counts on past zero until dismissed
+    overtime: bool,
+    clock: SharedClock,
 }
 
 impl Timer {
     pub fn new(duration: Duration, name: String) -> Self {
+        Timer::with_clock(duration, name, clock::system_clock())
+    }
+
+    pub fn builder(duration: Duration) -> TimerBuilder {
+        TimerBuilder {
+            duration,
+            name: "Timer".to_string(),
+            overtime: false,
+            clock: clock::system_clock(),
+        }
+    }
+
+    pub fn with_clock(duration: Duration, name: String, clock: SharedClock) -> Self {
         Timer {
-            started_at: Instant::now(),
+            started_at: clock.now(),
+            wall_started_at: clock.wall_now(),
             elapsed: Duration::ZERO,
             duration,
             status: TimerStatus::Running,
             name,
+            overtime: false,
+            clock,
         }
     }
 
+    pub fn snapshot(&self) -> TimerSnapshot {
+        TimerSnapshot {
+            name: self.name.clone(),
+            duration: self.duration,
+            elapsed: self.elapsed_time(),
+            status: self.status,
+            deadline: self
+                .deadline()
+                .and_then(|deadline| deadline.duration_since(UNIX_EPOCH).ok())
+                .map(|deadline| deadline.as_secs()),
+            overtime: self.overtime,
+        }
+    }
+
+    pub fn restore(snapshot: TimerSnapshot, clock: SharedClock) -> Self {
+        let mut timer = Timer::with_clock(snapshot.duration, snapshot.name, clock);
+        timer.elapsed = snapshot.elapsed;
+        timer.status = snapshot.status;
+        timer.overtime = snapshot.overtime;
+        timer
+    }
+
+    pub fn get_clock(&self) -> SharedClock {
+        self.clock.clone()
+    }
+
+    fn since_started(&self) -> Duration {
+        self.clock.now().saturating_duration_since(self.started_at)
+    }
+
     pub fn tick(&mut self) {
-        if self.status == TimerStatus::Running && self.is_done() {
+        if self.status == TimerStatus::Running && self.is_done() && !self.overtime {
             self.status = TimerStatus::Exit;
         }
     }
@@ -51,11 +185,12 @@ impl Timer {
     pub fn toggle_pause(&mut self) {
         match self.status {
             TimerStatus::Running => {
-                self.elapsed += self.started_at.elapsed();
+                self.elapsed += self.since_started();
                 self.status = TimerStatus::Paused;
             }
             TimerStatus::Paused => {
-                self.started_at = Instant::now();
+                self.started_at = self.clock.now();
+                self.wall_started_at = self.clock.wall_now();
                 self.status = TimerStatus::Running;
             }
             _ => {}
@@ -64,26 +199,97 @@ impl Timer {
 
     pub fn elapsed_time(&self) -> Duration {
         match self.status {
-            TimerStatus::Running => self.elapsed + self.started_at.elapsed(),
+            TimerStatus::Running => self.elapsed + self.since_started(),
             TimerStatus::Paused | TimerStatus::Done => self.elapsed,
             _ => self.elapsed,
         }
     }
 
+    /// Time the wall clock moved on while the monotonic clock didn't, i.e. the machine slept.
+    pub fn suspend_gap(&self) -> Option<Duration> {
+        if self.status != TimerStatus::Running {
+            return None;
+        }
+
+        // A wall clock set backwards errors here and is never mistaken for a suspend
+        let wall_elapsed = self
+            .clock
+            .wall_now()
+            .duration_since(self.wall_started_at)
+            .ok()?;
+        let gap = wall_elapsed.saturating_sub(self.since_started());
+        (gap > SUSPEND_THRESHOLD).then_some(gap)
+    }
+
+    /// Counts a suspend gap as elapsed time.
+    pub fn count_gap(&mut self, gap: Duration) {
+        self.elapsed += gap;
+        self.resync_wall_clock();
+    }
+
+    /// Lines the wall clock back up with the monotonic one, dropping any gap between them.
+    pub fn resync_wall_clock(&mut self) {
+        let now = self.clock.wall_now();
+        self.wall_started_at = now.checked_sub(self.since_started()).unwrap_or(now);
+    }
+
+    /// Wall-clock time at which the timer runs out, if it keeps running.
+    pub fn deadline(&self) -> Option<SystemTime> {
+        if self.status != TimerStatus::Running {
+            return None;
+        }
+        self.clock
+            .wall_now()
+            .checked_add(self.duration.saturating_sub(self.elapsed_time()))
+    }
+
+    /// Time left, rounded up to the whole second. Only a partial second rounds up, so a timer
+    /// that hasn't started yet shows its full length rather than a second more.
     pub fn remaining_time(&self) -> Duration {
         let precise_remaining = self.duration.saturating_sub(self.elapsed_time());
-        if precise_remaining.is_zero() {
-            return Duration::ZERO;
-        }
 
         // Round up for display: this is necessary because Duration includes fractional seconds
-        Duration::from_secs(precise_remaining.as_secs().saturating_add(1))
+        let partial_second = u64::from(precise_remaining.subsec_nanos() > 0);
+        Duration::from_secs(precise_remaining.as_secs().saturating_add(partial_second))
+    }
+
+    /// Like `remaining_time`, but negative by the whole seconds past the deadline once overrun.
+    pub fn signed_remaining_secs(&self) -> i64 {
+        let (seconds, sign) = match self.is_done() {
+            true => (self.overrun().as_secs(), -1),
+            false => (self.remaining_time().as_secs(), 1),
+        };
+        i64::try_from(seconds).unwrap_or(i64::MAX) * sign
+    }
+
+    /// How far the timer ran past its deadline.
+    pub fn overrun(&self) -> Duration {
+        self.elapsed_time().saturating_sub(self.duration)
     }
 
     pub fn is_done(&self) -> bool {
         self.elapsed_time() >= self.duration
     }
 
+    /// Whether the timer ran out and is counting on until dismissed.
+    pub fn is_overtime(&self) -> bool {
+        self.overtime && self.is_done()
+    }
+
+    pub fn set_overtime(&mut self, overtime: bool) {
+        self.overtime = overtime;
+    }
+
+    /// Stops a timer, keeping the time it ran for, e.g. how far it overran.
+    pub fn dismiss(&mut self) {
+        self.elapsed = self.elapsed_time();
+        self.status = TimerStatus::Exit;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.status == TimerStatus::Paused
+    }
+
     pub fn get_status(&self) -> TimerStatus {
         self.status
     }
@@ -96,10 +302,40 @@ impl Timer {
         self.duration
     }
 
+    /// Whether this counts up without end, like a stopwatch, rather than down to a deadline.
+    pub fn counts_up(&self) -> bool {
+        counts_up(self.duration)
+    }
+
+    pub fn extend(&mut self, duration: Duration) {
+        self.duration = self.duration.saturating_add(duration);
+    }
+
+    /// Shortens the timer so it runs out no later than `left` from now.
+    pub fn end_within(&mut self, left: Duration) {
+        self.duration = self.duration.min(self.elapsed_time().saturating_add(left));
+    }
+
+    /// Starts the current run over from zero, staying paused if it was.
+    pub fn restart(&mut self) {
+        self.elapsed = Duration::ZERO;
+        self.started_at = self.clock.now();
+        self.wall_started_at = self.clock.wall_now();
+    }
+
     pub fn get_name(&self) -> &str {
         &self.name
     }
 
+    /// Formats a signed remaining time, time past the deadline as `+3m 12s`.
+    pub fn format_signed_duration(&self, seconds: i64) -> String {
+        let formatted = self.format_duration(Duration::from_secs(seconds.unsigned_abs()));
+        match seconds < 0 {
+            true => format!("+{}", formatted),
+            false => formatted,
+        }
+    }
+
     pub fn format_duration(&self, total_seconds: Duration) -> String {
         let total_seconds = total_seconds.as_secs();
         match total_seconds {
@@ -161,6 +397,12 @@ impl TimerSession {
             timer: Timer::new(duration, name),
         }
     }
+
+    pub fn restore(snapshot: TimerSnapshot) -> Self {
+        TimerSession {
+            timer: Timer::restore(snapshot, clock::system_clock()),
+        }
+    }
 }
 
 impl Session for TimerSession {
@@ -180,14 +422,201 @@ impl Session for TimerSession {
         Some(&mut self.timer)
     }
 
-    fn get_pomodoro(&mut self) -> Option<&mut Pomodoro> {
-        None
+    fn snapshot(&self) -> SessionSnapshot {
+        SessionSnapshot::Timer(self.timer.snapshot())
+    }
+
+    fn restore(&mut self, snapshot: SessionSnapshot) -> Result<(), SnapshotMismatch> {
+        match snapshot {
+            SessionSnapshot::Timer(snapshot) => {
+                self.timer = Timer::restore(snapshot, self.timer.get_clock());
+                Ok(())
+            }
+            _ => Err(SnapshotMismatch),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::{Clock, ManualClock};
+    use std::sync::Arc;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn manual_timer(duration: Duration) -> (Timer, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock::new());
+        let timer = Timer::builder(duration)
+            .name("Focus")
+            .clock(clock.clone())
+            .build();
+        (timer, clock)
+    }
+
+    #[test]
+    fn test_elapsed_and_remaining() {
+        let (mut timer, clock) = manual_timer(25 * MINUTE);
+        assert_eq!(timer.remaining_time(), 25 * MINUTE);
+
+        clock.advance(Duration::from_millis(1500));
+        assert_eq!(timer.elapsed_time(), Duration::from_millis(1500));
+        // Rounded up to the whole second still to go
+        assert_eq!(timer.remaining_time(), 25 * MINUTE - Duration::from_secs(1));
+
+        clock.advance(25 * MINUTE);
+        assert!(timer.is_done());
+        assert_eq!(timer.remaining_time(), Duration::ZERO);
+
+        timer.tick();
+        assert!(timer.get_status() == TimerStatus::Exit);
+    }
+
+    #[test]
+    fn test_remaining_rounds_up_partial_seconds() {
+        let (timer, clock) = manual_timer(MINUTE);
+
+        // Whole seconds left are shown as they are
+        clock.advance(Duration::from_secs(20));
+        assert_eq!(timer.remaining_time(), Duration::from_secs(40));
+
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(timer.remaining_time(), Duration::from_secs(40));
+
+        clock.advance(Duration::from_millis(39_998));
+        assert_eq!(timer.remaining_time(), Duration::from_secs(1));
+
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(timer.remaining_time(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_pause() {
+        let (mut timer, clock) = manual_timer(25 * MINUTE);
+
+        clock.advance(10 * MINUTE);
+        timer.toggle_pause();
+        assert!(timer.is_paused());
+        assert_eq!(timer.deadline(), None);
+
+        clock.advance(60 * MINUTE);
+        assert_eq!(timer.elapsed_time(), 10 * MINUTE);
+        assert!(!timer.is_done());
+
+        timer.toggle_pause();
+        clock.advance(5 * MINUTE);
+        assert_eq!(timer.elapsed_time(), 15 * MINUTE);
+        assert_eq!(timer.deadline(), Some(clock.wall_now() + 10 * MINUTE));
+
+        timer.extend(5 * MINUTE);
+        assert_eq!(timer.remaining_time(), 15 * MINUTE);
+    }
+
+    #[test]
+    fn test_restart() {
+        let (mut timer, clock) = manual_timer(25 * MINUTE);
+
+        clock.advance(10 * MINUTE);
+        timer.restart();
+        assert_eq!(timer.remaining_time(), 25 * MINUTE);
+        clock.advance(MINUTE);
+        assert_eq!(timer.elapsed_time(), MINUTE);
+
+        // A paused timer starts over but stays paused
+        timer.toggle_pause();
+        timer.restart();
+        clock.advance(MINUTE);
+        assert!(timer.is_paused());
+        assert_eq!(timer.elapsed_time(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_overtime() {
+        let clock = Arc::new(ManualClock::new());
+        let mut timer = Timer::builder(25 * MINUTE)
+            .overtime(true)
+            .clock(clock.clone())
+            .build();
+
+        clock.advance(MINUTE + Duration::from_millis(500));
+        assert_eq!(timer.signed_remaining_secs(), 24 * 60);
+        assert!(!timer.is_overtime());
+
+        clock.advance(27 * MINUTE + Duration::from_secs(11));
+        timer.tick();
+        assert_eq!(timer.get_status(), TimerStatus::Running);
+        assert!(timer.is_overtime());
+        assert_eq!(timer.signed_remaining_secs(), -(3 * 60 + 11));
+        assert_eq!(
+            timer.format_signed_duration(timer.signed_remaining_secs()),
+            "+3m 11s"
+        );
+
+        let restored = Timer::restore(timer.snapshot(), clock.clone());
+        assert!(restored.is_overtime());
+
+        // The overrun stays put once dismissed
+        timer.dismiss();
+        clock.advance(MINUTE);
+        assert_eq!(timer.get_status(), TimerStatus::Exit);
+        assert_eq!(timer.overrun(), Duration::from_millis(3 * 60_000 + 11_500));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let (mut timer, clock) = manual_timer(25 * MINUTE);
+        clock.advance(10 * MINUTE);
+
+        let snapshot = timer.snapshot();
+        assert_eq!(snapshot.elapsed, 10 * MINUTE);
+        assert_eq!(snapshot.status, TimerStatus::Running);
+        assert!(snapshot.deadline.is_some());
+
+        timer.toggle_pause();
+        let json = serde_json::to_string(&timer.snapshot()).unwrap();
+        let snapshot: TimerSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(snapshot.deadline, None);
+
+        let mut restored = Timer::restore(snapshot, clock.clone());
+        clock.advance(5 * MINUTE);
+        assert!(restored.is_paused());
+        assert_eq!(restored.remaining_time(), 15 * MINUTE);
+
+        restored.toggle_pause();
+        clock.advance(5 * MINUTE);
+        assert_eq!(restored.remaining_time(), 10 * MINUTE);
+    }
+
+    #[test]
+    fn test_suspend_gap() {
+        let (mut timer, clock) = manual_timer(25 * MINUTE);
+        assert_eq!(timer.suspend_gap(), None);
+
+        clock.advance(5 * MINUTE);
+        clock.suspend(60 * MINUTE);
+        assert_eq!(timer.suspend_gap(), Some(60 * MINUTE));
+        // Instant stopped while suspended, so the timer alone doesn't notice
+        assert_eq!(timer.elapsed_time(), 5 * MINUTE);
+
+        timer.count_gap(60 * MINUTE);
+        assert_eq!(timer.suspend_gap(), None);
+        assert!(timer.is_done());
+
+        // Pausing and resuming re-anchors the wall clock
+        let (mut timer, clock) = manual_timer(25 * MINUTE);
+        clock.suspend(60 * MINUTE);
+        timer.toggle_pause();
+        assert_eq!(timer.suspend_gap(), None);
+        timer.toggle_pause();
+        assert_eq!(timer.suspend_gap(), None);
+
+        // Short hiccups and a clock set backwards aren't suspends
+        let (mut timer, clock) = manual_timer(25 * MINUTE);
+        clock.suspend(Duration::from_secs(2));
+        assert_eq!(timer.suspend_gap(), None);
+        timer.wall_started_at += 60 * MINUTE;
+        assert_eq!(timer.suspend_gap(), None);
+    }
 
     #[test]
     fn test_format_duration() {
@@ -1,19 +1,31 @@
 use ratatui::{
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    widgets::{Block, BorderType, Borders, Gauge, Paragraph},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    widgets::{Block, BorderType, Borders, Gauge, Paragraph, Row, Table},
     Frame,
 };
 
-use crate::app::App;
+use crate::app::{App, PlanInfo, SessionEntry, SessionInfo};
+use crate::pomodoro::PomodoroState;
+use crate::stopwatch::Lap;
 use crate::timer::TimerStatus;
 
 pub fn render(f: &mut Frame, app: &mut App) {
     let area = f.size();
+    let entries = app.get_session_entries();
+    let list_height = match entries.len() {
+        0 => 0,
+        count => count as u16 + 2,
+    };
 
     let vertical_layout = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(3)])
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(list_height),
+            Constraint::Min(0),
+        ])
         .horizontal_margin(1)
         .split(area);
 
@@ -22,8 +34,36 @@ pub fn render(f: &mut Frame, app: &mut App) {
         .constraints([Constraint::Ratio(2, 3)])
         .split(vertical_layout[0]);
 
+    let info_layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Ratio(2, 3)])
+        .split(vertical_layout[1]);
+
+    let list_layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Ratio(2, 3)])
+        .split(vertical_layout[2]);
+
+    let laps_layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Ratio(2, 3)])
+        .split(vertical_layout[3]);
+
+    let info = app.get_timer().is_some().then(|| app.get_session_info());
+    // A plan step that ran out stays on screen until moved on from
+    let up_next = info
+        .as_ref()
+        .and_then(|info| info.plan.as_ref())
+        .filter(|plan| plan.waiting)
+        .map(|plan| {
+            plan.upcoming
+                .first()
+                .map_or("", |step| step.name.as_str())
+                .to_string()
+        });
+
     match app.get_timer() {
-        Some(timer) if timer.get_status() == TimerStatus::Done => {
+        Some(timer) if timer.get_status() == TimerStatus::Done && up_next.is_none() => {
             // Done UI:
             f.render_widget(
                 Paragraph::new("Timer is done! You made it!")
@@ -31,21 +71,70 @@ pub fn render(f: &mut Frame, app: &mut App) {
                 f.size(),
             )
         }
+        Some(timer) if counting_up(info.as_ref()) => {
+            // Stopwatch UI: counts up, so there's no progress to show
+            let elapsed = timer.format_duration(timer.elapsed_time());
+            let title = match timer.is_paused() {
+                true => format!("{} (paused)", timer.get_name()),
+                false => timer.get_name().to_string(),
+            };
+
+            f.render_widget(
+                Paragraph::new(elapsed)
+                    .style(
+                        Style::default()
+                            .fg(Color::Magenta)
+                            .add_modifier(Modifier::BOLD),
+                    )
+                    .block(
+                        Block::bordered()
+                            .border_type(BorderType::Rounded)
+                            .title(title),
+                    ),
+                horizontal_layout[0],
+            );
+        }
         Some(timer) => {
             // Running UI:
             let ratio =
                 (timer.elapsed_time().as_secs_f64() / timer.get_duration().as_secs_f64()).min(1.0);
-            let label = timer.to_string();
+            let (name, remaining) = info
+                .as_ref()
+                .map(|info| (info.timer.name.clone(), info.timer.remaining))
+                .unwrap_or_default();
+            let ready = info
+                .as_ref()
+                .and_then(|info| info.pomodoro.as_ref())
+                .is_some_and(|pomodoro| pomodoro.state == PomodoroState::Ready);
+            let overtime = timer.is_overtime();
+            let (name, label) = if let Some(next) = up_next {
+                (
+                    format!("Up next: {}", next),
+                    "Press [Enter] to continue".to_string(),
+                )
+            } else if ready {
+                (
+                    format!("Ready for {}", name.to_lowercase()),
+                    "Press [Enter] to start".to_string(),
+                )
+            } else if overtime {
+                (
+                    format!("{} (overtime)", name),
+                    timer.format_signed_duration(timer.signed_remaining_secs()),
+                )
+            } else {
+                (name, timer.format_duration(remaining))
+            };
 
             let progress = Gauge::default()
                 .block(
                     Block::bordered()
                         .border_type(BorderType::Rounded)
-                        .title(timer.get_name()),
+                        .title(name),
                 )
                 .gauge_style(
                     Style::default()
-                        .fg(Color::Magenta)
+                        .fg(if overtime { Color::Red } else { Color::Magenta })
                         .bg(Color::Black)
                         .add_modifier(Modifier::ITALIC | Modifier::BOLD),
                 )
@@ -62,4 +151,304 @@ pub fn render(f: &mut Frame, app: &mut App) {
             );
         }
     }
+
+    let (pomodoro, mob, standup, stopwatch, plan, flowtime) = info
+        .map(|info| {
+            (
+                info.pomodoro,
+                info.mob,
+                info.standup,
+                info.stopwatch,
+                info.plan,
+                info.flowtime,
+            )
+        })
+        .unwrap_or_default();
+    let mut lines = Vec::new();
+
+    if let Some(notice) = app.get_notice() {
+        lines.push(notice.to_string());
+    }
+
+    if let Some(mob) = mob {
+        lines.push(format!(
+            "Rotation {} | Driving: {} | Next: {}",
+            mob.rotation,
+            mob.driver.as_deref().unwrap_or("On break"),
+            mob.next_driver.as_deref().unwrap_or("-"),
+        ));
+    }
+
+    if let Some(pomodoro) = &pomodoro {
+        let mut line = match pomodoro.total_sessions {
+            0 => format!("Session {}", pomodoro.current_session),
+            total => format!("Session {}/{}", pomodoro.current_session, total),
+        };
+        if pomodoro.long_break_every > 0 {
+            line.push_str(&format!(
+                " | Set {}/{}",
+                pomodoro.set_position, pomodoro.long_break_every
+            ));
+        }
+        line.push_str(&format!(" | Done today: {}", pomodoro.completed_today));
+        if let (Some(next), Some(timer)) = (pomodoro.next_phase, app.get_timer()) {
+            line.push_str(&format!(
+                " | Next: {} {}",
+                phase_name(next),
+                timer.format_duration(pomodoro.next_duration)
+            ));
+        }
+        if !pomodoro.waited.is_zero() {
+            if let Some(timer) = app.get_timer() {
+                line.push_str(&format!(
+                    " | Waited {}",
+                    timer.format_duration(pomodoro.waited)
+                ));
+            }
+        }
+        if !pomodoro.overrun.is_zero() {
+            if let Some(timer) = app.get_timer() {
+                line.push_str(&format!(
+                    " | Overran {}",
+                    timer.format_duration(pomodoro.overrun)
+                ));
+            }
+        }
+        lines.push(line);
+    }
+
+    let focusing = pomodoro
+        .as_ref()
+        .is_some_and(|pomodoro| matches!(pomodoro.state, PomodoroState::Focus(_)));
+
+    if let Some(flowtime) = &flowtime {
+        if let Some(timer) = app.get_timer() {
+            let mut line = format!("Focused: {}", timer.format_duration(flowtime.focused));
+            if focusing {
+                line.push_str(&format!(
+                    " | Break earned: {}",
+                    timer.format_duration(flowtime.earned_break)
+                ));
+            }
+            lines.push(line);
+        }
+    }
+
+    if let Some(standup) = standup {
+        lines.push(format!(
+            "Speaker {}/{} | Next: {}",
+            standup.current_speaker,
+            standup.total_speakers,
+            standup.next_speaker.as_deref().unwrap_or("-"),
+        ));
+        if !standup.overrun.is_zero() {
+            lines.push(format!("Over by {}s", standup.overrun.as_secs()));
+        }
+        lines.push("[Enter] next speaker".to_string());
+    }
+
+    if let Some(plan) = &plan {
+        if let Some(timer) = app.get_timer() {
+            lines.push(format!(
+                "Step {}/{} | Left: {}",
+                plan.current_step,
+                plan.total_steps,
+                timer.format_duration(plan.remaining)
+            ));
+        }
+        if plan.waiting {
+            lines.push("[Enter] next step".to_string());
+        }
+    }
+
+    let away = app.get_suspend_gap().and_then(|gap| {
+        app.get_timer()
+            .map(|timer| format!("Away for {}: count it? [y/n]", timer.format_duration(gap)))
+    });
+
+    let overtime = app.get_timer().is_some_and(|timer| timer.is_overtime());
+
+    match app.get_vote_info() {
+        _ if away.is_some() => lines.extend(away),
+        _ if app.is_extra_selected() => {
+            lines.push("[p]ause, [s]top, [r]estart, [+]5 min, [x] remove, [Tab] switch".to_string())
+        }
+        _ if overtime => lines.push("[Enter] dismiss overtime".to_string()),
+        Some(vote) => lines.push(format!(
+            "Vote: {} ({} yes, {} no) [y/n]",
+            vote.proposal, vote.yes, vote.no
+        )),
+        None if flowtime.is_some() && focusing => {
+            lines.push("[Enter] take a break, [p]ause".to_string())
+        }
+        None if flowtime.is_some() => lines.push("[Enter] back to focus, [+]5 min".to_string()),
+        None if pomodoro.is_some() => {
+            lines.push("Propose: [S]kip break, [E]xtend focus, [N]ext session".to_string());
+            lines.push("[s]kip, [r]estart, [+]5 min".to_string());
+        }
+        None if stopwatch.is_some() => lines.push("[l]ap, [r]eset".to_string()),
+        None => lines.push("[s]kip, [r]estart, [+]5 min".to_string()),
+    }
+    lines.push(match entries.is_empty() {
+        true => "[a]dd timer".to_string(),
+        false => "[a]dd timer, [Tab] switch".to_string(),
+    });
+
+    // Typing a new timer takes over the session box
+    if let Some((prompt, error)) = app.get_prompt() {
+        lines = vec![format!("New timer (e.g. tea 4m, call @10:00): {}_", prompt)];
+        lines.extend(error.map(str::to_string));
+        lines.push("[Enter] add, [Esc] cancel".to_string());
+    }
+
+    if !lines.is_empty() {
+        f.render_widget(
+            Paragraph::new(lines.join(" | ")).block(
+                Block::bordered()
+                    .border_type(BorderType::Rounded)
+                    .title("Session"),
+            ),
+            info_layout[0],
+        );
+    }
+
+    if !entries.is_empty() {
+        render_entries(f, &entries, list_layout[0]);
+    }
+
+    if let Some(stopwatch) = stopwatch {
+        render_laps(f, app, &stopwatch.laps, laps_layout[0]);
+    }
+
+    if let Some(plan) = plan {
+        render_steps(f, app, &plan, laps_layout[0]);
+    }
+}
+
+fn phase_name(phase: PomodoroState) -> &'static str {
+    match phase {
+        PomodoroState::Focus(_) => "focus",
+        PomodoroState::Break(_) => "break",
+        PomodoroState::LongBreak(_) => "long break",
+        PomodoroState::Ready | PomodoroState::Completed => "done",
+    }
+}
+
+/// Whether the session counts up rather than down, like a stopwatch or a flowtime focus.
+fn counting_up(info: Option<&SessionInfo>) -> bool {
+    info.is_some_and(|info| {
+        info.stopwatch.is_some()
+            || info.flowtime.is_some()
+                && info
+                    .pomodoro
+                    .as_ref()
+                    .is_some_and(|pomodoro| matches!(pomodoro.state, PomodoroState::Focus(_)))
+    })
+}
+
+fn render_entries(f: &mut Frame, entries: &[SessionEntry], area: Rect) {
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .title("Sessions");
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(entries.iter().map(|_| Constraint::Length(1)))
+        .split(block.inner(area));
+    f.render_widget(block, area);
+
+    for (entry, row) in entries.iter().zip(rows.iter()) {
+        let marker = if entry.selected { ">" } else { " " };
+        let paused = if entry.paused { " (paused)" } else { "" };
+        let color = if entry.overtime {
+            Color::Red
+        } else if entry.selected {
+            Color::Magenta
+        } else {
+            Color::DarkGray
+        };
+
+        f.render_widget(
+            Gauge::default()
+                .gauge_style(Style::default().fg(color).bg(Color::Black))
+                .use_unicode(true)
+                .label(format!(
+                    "{} {} {}{}",
+                    marker, entry.name, entry.label, paused
+                ))
+                .ratio(entry.ratio),
+            *row,
+        );
+    }
+}
+
+fn render_steps(f: &mut Frame, app: &mut App, plan: &PlanInfo, area: Rect) {
+    let Some(timer) = app.get_timer() else {
+        return;
+    };
+
+    let current = Row::new(vec![
+        format!("{}", plan.current_step),
+        timer.get_name().to_string(),
+        timer.format_duration(timer.remaining_time()),
+    ])
+    .style(Style::default().fg(Color::Magenta).bold());
+    let upcoming = plan.upcoming.iter().enumerate().map(|(index, step)| {
+        Row::new(vec![
+            format!("{}", plan.current_step + index + 1),
+            step.name.clone(),
+            timer.format_duration(step.duration),
+        ])
+    });
+    let widths = [
+        Constraint::Length(6),
+        Constraint::Min(12),
+        Constraint::Length(12),
+    ];
+    let title = format!(
+        "{} ({} left)",
+        plan.title.as_deref().unwrap_or("Plan"),
+        timer.format_duration(plan.remaining)
+    );
+
+    f.render_widget(
+        Table::new(std::iter::once(current).chain(upcoming), widths)
+            .header(Row::new(vec!["Step", "Name", "Duration"]).style(Style::default().bold()))
+            .block(
+                Block::bordered()
+                    .border_type(BorderType::Rounded)
+                    .title(title),
+            ),
+        area,
+    );
+}
+
+fn render_laps(f: &mut Frame, app: &mut App, laps: &[Lap], area: Rect) {
+    let Some(timer) = app.get_timer() else {
+        return;
+    };
+
+    // Newest lap first, so it stays in view
+    let rows = laps.iter().rev().map(|lap| {
+        Row::new(vec![
+            format!("#{}", lap.number),
+            timer.format_duration(lap.split),
+            timer.format_duration(lap.total),
+        ])
+    });
+    let widths = [
+        Constraint::Length(6),
+        Constraint::Length(12),
+        Constraint::Length(12),
+    ];
+
+    f.render_widget(
+        Table::new(rows, widths)
+            .header(Row::new(vec!["Lap", "Split", "Total"]).style(Style::default().bold()))
+            .block(
+                Block::bordered()
+                    .border_type(BorderType::Rounded)
+                    .title("Laps"),
+            ),
+        area,
+    );
 }
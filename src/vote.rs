@@ -0,0 +1,171 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+pub const EXTEND_FOCUS_DURATION: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Debug)]
+pub enum Proposal {
+    SkipBreak,
+    ExtendFocus(Duration),
+    StartNextSession,
+    /// What `s`, `r` and `+` do alone, put to a vote in a shared session
+    Skip,
+    Restart,
+    Extend(Duration),
+}
+
+#[derive(Clone, Copy, PartialEq, Default, Debug, ValueEnum)]
+pub enum VoteRule {
+    /// More than half of the participants approve
+    #[default]
+    Majority,
+    /// Every participant approves
+    Unanimous,
+    /// A majority that includes the host approves, the host alone can reject
+    HostVeto,
+}
+
+#[derive(Clone, Debug)]
+pub struct Ballot {
+    proposal: Proposal,
+    votes: HashMap<SocketAddr, bool>,
+}
+
+impl Ballot {
+    pub fn new(proposal: Proposal, proposer: SocketAddr) -> Self {
+        // The proposer implicitly approves their own proposal
+        Ballot {
+            proposal,
+            votes: HashMap::from([(proposer, true)]),
+        }
+    }
+
+    pub fn cast(&mut self, voter: SocketAddr, approve: bool) {
+        self.votes.insert(voter, approve);
+    }
+
+    pub fn get_proposal(&self) -> Proposal {
+        self.proposal
+    }
+
+    pub fn yes(&self) -> usize {
+        self.votes.values().filter(|&&approve| approve).count()
+    }
+
+    pub fn no(&self) -> usize {
+        self.votes.values().filter(|&&approve| !approve).count()
+    }
+
+    /// Returns `Some(passed)` once the outcome can no longer change, `None` while it is still open.
+    pub fn tally(&self, rule: VoteRule, participants: usize, host: SocketAddr) -> Option<bool> {
+        let (yes, no) = (self.yes(), self.no());
+
+        match rule {
+            VoteRule::Majority => {
+                if yes * 2 > participants {
+                    Some(true)
+                } else if no * 2 >= participants {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            VoteRule::Unanimous => {
+                if no > 0 {
+                    Some(false)
+                } else if yes >= participants {
+                    Some(true)
+                } else {
+                    None
+                }
+            }
+            VoteRule::HostVeto => match self.votes.get(&host) {
+                Some(false) => Some(false),
+                Some(true) if yes * 2 > participants => Some(true),
+                _ if no * 2 >= participants => Some(false),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Proposal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Proposal::SkipBreak => write!(f, "Skip break"),
+            Proposal::ExtendFocus(duration) => {
+                write!(f, "Extend focus {}m", duration.as_secs() / 60)
+            }
+            Proposal::StartNextSession => write!(f, "Start next session"),
+            Proposal::Skip => write!(f, "Skip"),
+            Proposal::Restart => write!(f, "Restart"),
+            Proposal::Extend(duration) => write!(f, "Extend {}m", duration.as_secs() / 60),
+        }
+    }
+}
+
+impl fmt::Display for VoteRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VoteRule::Majority => write!(f, "majority"),
+            VoteRule::Unanimous => write!(f, "unanimous"),
+            VoteRule::HostVeto => write!(f, "host veto"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn test_majority() {
+        let mut ballot = Ballot::new(Proposal::SkipBreak, addr(1));
+        assert_eq!(ballot.tally(VoteRule::Majority, 3, addr(1)), None);
+
+        ballot.cast(addr(2), true);
+        assert_eq!(ballot.tally(VoteRule::Majority, 3, addr(1)), Some(true));
+
+        let mut ballot = Ballot::new(Proposal::SkipBreak, addr(1));
+        ballot.cast(addr(2), false);
+        assert_eq!(ballot.tally(VoteRule::Majority, 4, addr(1)), None);
+        ballot.cast(addr(3), false);
+        assert_eq!(ballot.tally(VoteRule::Majority, 4, addr(1)), Some(false));
+    }
+
+    #[test]
+    fn test_unanimous() {
+        let mut ballot = Ballot::new(Proposal::StartNextSession, addr(1));
+        ballot.cast(addr(2), true);
+        assert_eq!(ballot.tally(VoteRule::Unanimous, 3, addr(1)), None);
+
+        ballot.cast(addr(3), true);
+        assert_eq!(ballot.tally(VoteRule::Unanimous, 3, addr(1)), Some(true));
+
+        ballot.cast(addr(3), false);
+        assert_eq!(ballot.tally(VoteRule::Unanimous, 3, addr(1)), Some(false));
+    }
+
+    #[test]
+    fn test_host_veto() {
+        let host = addr(1);
+
+        let mut ballot = Ballot::new(Proposal::ExtendFocus(EXTEND_FOCUS_DURATION), addr(2));
+        ballot.cast(addr(3), true);
+        assert_eq!(ballot.tally(VoteRule::HostVeto, 3, host), None);
+
+        ballot.cast(host, false);
+        assert_eq!(ballot.tally(VoteRule::HostVeto, 3, host), Some(false));
+
+        ballot.cast(host, true);
+        assert_eq!(ballot.tally(VoteRule::HostVeto, 3, host), Some(true));
+    }
+}
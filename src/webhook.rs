@@ -0,0 +1,420 @@
+use crate::app::SessionInfo;
+use crate::pomodoro::PomodoroState;
+
+use serde::Serialize;
+use std::io;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::debug;
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(5);
+const DELIVERY_ATTEMPTS: u32 = 3;
+const RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+#[derive(Error, Debug, PartialEq)]
+pub enum WebhookUrlError {
+    #[error("Only plain `http://` webhook URLs are supported: `{0}`")]
+    UnsupportedScheme(String),
+
+    #[error("Invalid port in webhook URL: `{0}`")]
+    InvalidPort(String),
+
+    #[error("Missing host in webhook URL: `{0}`")]
+    MissingHost(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WebhookUrl {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl FromStr for WebhookUrl {
+    type Err = WebhookUrlError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let rest = input
+            .strip_prefix("http://")
+            .ok_or_else(|| WebhookUrlError::UnsupportedScheme(input.to_string()))?;
+
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .map_err(|_| WebhookUrlError::InvalidPort(input.to_string()))?,
+            ),
+            None => (authority, 80),
+        };
+
+        if host.is_empty() {
+            return Err(WebhookUrlError::MissingHost(input.to_string()));
+        }
+
+        Ok(WebhookUrl {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    FocusStarted,
+    FocusEnded,
+    BreakStarted,
+    CycleCompleted,
+    Quit,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct WebhookPayload {
+    pub event: WebhookEvent,
+    pub phase: Option<PomodoroState>,
+    pub session: usize,
+    pub total_sessions: usize,
+    pub planned_duration_secs: u64,
+    pub task: Option<String>,
+    pub phase_started_at: u64,
+    pub timestamp: u64,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[derive(Clone)]
+struct Phase {
+    state: PomodoroState,
+    session: usize,
+    total_sessions: usize,
+    planned: Duration,
+    started_at: u64,
+}
+
+/// Turns the pomodoro's phase changes into webhook deliveries. Deliveries run on their own
+/// tasks, so a slow or dead endpoint never holds up the app.
+pub struct WebhookNotifier {
+    sender: flume::Sender<WebhookPayload>,
+    dispatcher: Option<JoinHandle<()>>,
+    task: Option<String>,
+    last: Option<Phase>,
+}
+
+impl WebhookNotifier {
+    pub fn new(urls: Vec<WebhookUrl>, task: Option<String>) -> Self {
+        let (sender, receiver) = flume::unbounded::<WebhookPayload>();
+
+        // One queue per URL keeps each endpoint's events in order without one blocking another
+        let dispatcher = tokio::spawn(async move {
+            let mut deliveries = JoinSet::new();
+            let queues: Vec<flume::Sender<String>> = urls
+                .into_iter()
+                .map(|url| {
+                    let (queue, pending) = flume::unbounded::<String>();
+                    deliveries.spawn(async move {
+                        while let Ok(body) = pending.recv_async().await {
+                            deliver(&url, &body).await;
+                        }
+                    });
+                    queue
+                })
+                .collect();
+
+            while let Ok(payload) = receiver.recv_async().await {
+                let body = serde_json::to_string(&payload).unwrap();
+                for queue in &queues {
+                    let _ = queue.send(body.clone());
+                }
+            }
+
+            drop(queues);
+            while deliveries.join_next().await.is_some() {}
+        });
+
+        WebhookNotifier {
+            sender,
+            dispatcher: Some(dispatcher),
+            task,
+            last: None,
+        }
+    }
+
+    pub fn observe(&mut self, info: &SessionInfo) {
+        let Some(pomodoro) = &info.pomodoro else {
+            return;
+        };
+
+        if self.last.as_ref().map(|last| last.state) == Some(pomodoro.state) {
+            return;
+        }
+
+        let previous = self.last.replace(Phase {
+            state: pomodoro.state,
+            session: pomodoro.current_session,
+            total_sessions: pomodoro.total_sessions,
+            planned: info.timer.duration,
+            started_at: unix_now(),
+        });
+
+        if let Some(previous) =
+            previous.filter(|phase| matches!(phase.state, PomodoroState::Focus(_)))
+        {
+            self.send(WebhookEvent::FocusEnded, &previous);
+        }
+
+        let current = self.last.clone().unwrap();
+        match current.state {
+            PomodoroState::Focus(_) => self.send(WebhookEvent::FocusStarted, &current),
+            PomodoroState::Break(_) | PomodoroState::LongBreak(_) => {
+                self.send(WebhookEvent::BreakStarted, &current)
+            }
+            PomodoroState::Completed => self.send(WebhookEvent::CycleCompleted, &current),
+            PomodoroState::Ready => {}
+        }
+    }
+
+    /// Reports quitting instead of the cycle completing, ending the focus phase if one was running.
+    pub fn quit(&mut self, info: Option<&SessionInfo>) {
+        let previous = self.last.take();
+
+        if let Some(previous) = previous
+            .as_ref()
+            .filter(|phase| matches!(phase.state, PomodoroState::Focus(_)))
+        {
+            self.send(WebhookEvent::FocusEnded, previous);
+        }
+
+        let quit = Phase {
+            state: previous
+                .as_ref()
+                .map_or(PomodoroState::Completed, |phase| phase.state),
+            session: previous.as_ref().map_or(0, |phase| phase.session),
+            total_sessions: previous.as_ref().map_or(0, |phase| phase.total_sessions),
+            planned: info.map_or(Duration::ZERO, |info| info.timer.duration),
+            started_at: previous
+                .as_ref()
+                .map_or_else(unix_now, |phase| phase.started_at),
+        };
+        self.send(WebhookEvent::Quit, &quit);
+
+        // Quitting moves the pomodoro to `Completed`, which must not read as a finished cycle
+        self.last = Some(Phase {
+            state: PomodoroState::Completed,
+            ..quit
+        });
+    }
+
+    /// Gives deliveries still in flight one timeout's worth of time before the app exits.
+    pub async fn finish(self) {
+        let WebhookNotifier {
+            sender, dispatcher, ..
+        } = self;
+        drop(sender);
+
+        if let Some(dispatcher) = dispatcher {
+            let _ = tokio::time::timeout(DELIVERY_TIMEOUT, dispatcher).await;
+        }
+    }
+
+    fn send(&self, event: WebhookEvent, phase: &Phase) {
+        let payload = WebhookPayload {
+            event,
+            phase: Some(phase.state),
+            session: phase.session,
+            total_sessions: phase.total_sessions,
+            planned_duration_secs: phase.planned.as_secs(),
+            task: self.task.clone(),
+            phase_started_at: phase.started_at,
+            timestamp: unix_now(),
+        };
+
+        if self.sender.send(payload).is_err() {
+            debug!("WEBHOOK: Dispatcher is gone, dropping {:?}", event);
+        }
+    }
+}
+
+async fn deliver(url: &WebhookUrl, body: &str) {
+    for attempt in 1..=DELIVERY_ATTEMPTS {
+        match tokio::time::timeout(DELIVERY_TIMEOUT, post(url, body)).await {
+            Ok(Ok(())) => return,
+            Ok(Err(e)) => debug!("WEBHOOK: Attempt {} to {:?} failed: {:?}", attempt, url, e),
+            Err(_) => debug!("WEBHOOK: Attempt {} to {:?} timed out", attempt, url),
+        }
+
+        if attempt < DELIVERY_ATTEMPTS {
+            tokio::time::sleep(RETRY_BACKOFF * attempt).await;
+        }
+    }
+}
+
+async fn post(url: &WebhookUrl, body: &str) -> io::Result<()> {
+    let mut stream = TcpStream::connect((url.host.as_str(), url.port)).await?;
+
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        url.path,
+        url.host,
+        url.port,
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = [0; 256];
+    let n = stream.read(&mut response).await?;
+    let status_line = String::from_utf8_lossy(&response[..n]);
+
+    match status_line.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        code => Err(io::Error::other(format!("unexpected status: {:?}", code))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{PomodoroInfo, TimerInfo};
+
+    fn info(state: PomodoroState, session: usize) -> SessionInfo {
+        SessionInfo {
+            timer: TimerInfo {
+                name: "Focus".to_string(),
+                remaining: Duration::from_secs(60),
+                duration: Duration::from_secs(1500),
+                paused: false,
+                deadline: None,
+                overrun: Duration::ZERO,
+            },
+            pomodoro: Some(PomodoroInfo {
+                state,
+                total_sessions: 4,
+                long_break_every: 4,
+                set_position: session,
+                completed_today: session - 1,
+                current_session: session,
+                pending: None,
+                waited: Duration::ZERO,
+                overrun: Duration::ZERO,
+                next_phase: None,
+                next_duration: Duration::ZERO,
+            }),
+            mob: None,
+            standup: None,
+            stopwatch: None,
+            plan: None,
+            flowtime: None,
+        }
+    }
+
+    fn notifier() -> (WebhookNotifier, flume::Receiver<WebhookPayload>) {
+        let (sender, receiver) = flume::unbounded();
+        let notifier = WebhookNotifier {
+            sender,
+            dispatcher: None,
+            task: Some("Write report".to_string()),
+            last: None,
+        };
+        (notifier, receiver)
+    }
+
+    fn events(receiver: &flume::Receiver<WebhookPayload>) -> Vec<WebhookEvent> {
+        receiver.try_iter().map(|payload| payload.event).collect()
+    }
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            "http://localhost:9000/hooks/pomodoro".parse(),
+            Ok(WebhookUrl {
+                host: "localhost".to_string(),
+                port: 9000,
+                path: "/hooks/pomodoro".to_string(),
+            })
+        );
+        assert_eq!("http://example.com".parse::<WebhookUrl>().unwrap().port, 80);
+        assert!(matches!(
+            "https://example.com".parse::<WebhookUrl>(),
+            Err(WebhookUrlError::UnsupportedScheme(_))
+        ));
+        assert!(matches!(
+            "http://example.com:port/".parse::<WebhookUrl>(),
+            Err(WebhookUrlError::InvalidPort(_))
+        ));
+    }
+
+    #[test]
+    fn test_phase_transitions() {
+        let (mut notifier, receiver) = notifier();
+
+        notifier.observe(&info(PomodoroState::Focus(1), 1));
+        notifier.observe(&info(PomodoroState::Focus(1), 1));
+        assert_eq!(events(&receiver), [WebhookEvent::FocusStarted]);
+
+        notifier.observe(&info(PomodoroState::Break(1), 1));
+        assert_eq!(
+            events(&receiver),
+            [WebhookEvent::FocusEnded, WebhookEvent::BreakStarted]
+        );
+
+        notifier.observe(&info(PomodoroState::Completed, 4));
+        assert_eq!(events(&receiver), [WebhookEvent::CycleCompleted]);
+    }
+
+    #[test]
+    fn test_quit_during_focus() {
+        let (mut notifier, receiver) = notifier();
+
+        notifier.observe(&info(PomodoroState::Focus(2), 2));
+        receiver.try_iter().for_each(drop);
+
+        notifier.quit(Some(&info(PomodoroState::Focus(2), 2)));
+        notifier.observe(&info(PomodoroState::Completed, 2));
+
+        let payloads: Vec<WebhookPayload> = receiver.try_iter().collect();
+        assert_eq!(payloads.len(), 2);
+        assert_eq!(payloads[0].event, WebhookEvent::FocusEnded);
+        assert_eq!(payloads[0].session, 2);
+        assert_eq!(payloads[0].task.as_deref(), Some("Write report"));
+        assert_eq!(payloads[1].event, WebhookEvent::Quit);
+    }
+
+    #[tokio::test]
+    async fn test_deliver_posts_payload() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let n = socket.read(&mut request).await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&request[..n]).to_string()
+        });
+
+        let url: WebhookUrl = format!("http://127.0.0.1:{}/hook", port).parse().unwrap();
+        deliver(&url, r#"{"event":"quit"}"#).await;
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /hook HTTP/1.1"));
+        assert!(request.ends_with(r#"{"event":"quit"}"#));
+    }
+}
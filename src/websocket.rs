@@ -1,3 +1,4 @@
+use crate::metrics::METRICS;
 use crate::timer::TimerAction;
 
 use futures::{SinkExt, StreamExt};
@@ -8,13 +9,16 @@ use std::sync::Arc;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::Mutex;
 use tokio_tungstenite::tungstenite::client::IntoClientRequest;
-use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::{self, Message};
 use tokio_tungstenite::MaybeTlsStream;
 use tokio_tungstenite::WebSocketStream;
 use tracing::debug;
 
 type Sender = flume::Sender<TimerMessage>;
 type PeerMap = Arc<Mutex<HashMap<SocketAddr, Sender>>>;
+type NameMap = Arc<Mutex<HashMap<SocketAddr, String>>>;
+pub type ClientStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TimerMessage {
@@ -22,68 +26,147 @@ pub struct TimerMessage {
     pub sender: SocketAddr,
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Role {
+    Host,
+    Guest,
+}
+
 #[derive(Clone)]
 pub struct WebSocketHandler {
+    pub role: Role,
     pub peer_map: PeerMap,
+    pub peer_names: NameMap,
     pub ws_to_app_sender: flume::Sender<TimerMessage>,
     pub ws_to_app_receiver: flume::Receiver<TimerMessage>,
     pub app_to_ws_sender: flume::Sender<TimerMessage>,
     pub app_to_ws_receiver: flume::Receiver<TimerMessage>,
     pub local_addr: Arc<Mutex<Option<SocketAddr>>>,
+    pub local_name: Arc<Mutex<Option<String>>>,
 }
 
 impl WebSocketHandler {
-    pub fn new() -> Self {
+    pub fn new(role: Role) -> Self {
         let (app_to_ws_sender, app_to_ws_receiver) = flume::unbounded();
         let (ws_to_app_sender, ws_to_app_receiver) = flume::unbounded();
 
         WebSocketHandler {
+            role,
             peer_map: Arc::new(Mutex::new(HashMap::new())),
+            peer_names: Arc::new(Mutex::new(HashMap::new())),
             ws_to_app_sender,
             ws_to_app_receiver,
             app_to_ws_sender,
             app_to_ws_receiver,
             local_addr: Arc::new(Mutex::new(None)),
+            local_name: Arc::new(Mutex::new(None)),
         }
     }
 
-    pub async fn host(self, addr: &SocketAddr) {
+    pub async fn host(self, addr: &SocketAddr, name: String, browser_control: bool) {
         let listener = TcpListener::bind(addr).await.unwrap();
         *self.local_addr.lock().await = Some(*addr);
+        *self.local_name.lock().await = Some(name);
+        METRICS.room_opened();
 
         loop {
             tokio::select! {
                 Ok((socket, peer_addr)) = listener.accept() => {
-
-                    let ws_stream = tokio_tungstenite::accept_async(socket)
-                        .await
-                        .expect("Error during the websocket handshake occurred");
-
                     let handler_clone = self.clone();
                     tokio::spawn(async move {
-                        handler_clone.handle_connection(peer_addr, ws_stream).await;
+                        handler_clone.accept(socket, peer_addr, browser_control).await;
                     });
                 }
             }
         }
     }
 
-    async fn handle_connection(&self, addr: SocketAddr, ws_stream: WebSocketStream<TcpStream>) {
+    async fn accept(&self, mut socket: TcpStream, peer_addr: SocketAddr, browser_control: bool) {
+        // Browsers asking for the page and peers upgrading to a websocket share the same port
+        if !is_websocket_upgrade(&socket).await {
+            if let Err(e) = serve_page(&mut socket, browser_control).await {
+                debug!(
+                    "HOST(HTTP): Failed to serve page to {:?}: {:?}",
+                    peer_addr, e
+                );
+            }
+            return;
+        }
+
+        let mut query = None;
+        // The handshake callback's error type is dictated by tungstenite
+        #[allow(clippy::result_large_err)]
+        let ws_stream =
+            tokio_tungstenite::accept_hdr_async(socket, |request: &Request, response: Response| {
+                query = request.uri().query().map(str::to_string);
+                Ok(response)
+            })
+            .await;
+
+        let ws_stream = match ws_stream {
+            Ok(ws_stream) => ws_stream,
+            Err(e) => {
+                debug!("HOST(HANDSHAKE): Failed with {:?}: {:?}", peer_addr, e);
+                METRICS.handshake_failed();
+                return;
+            }
+        };
+
+        let query = query.unwrap_or_default();
+        let client = if query_value(&query, "client").as_deref() == Some("browser") {
+            Client::Browser { browser_control }
+        } else {
+            // Browsers only watch, they don't take part in votes or statistics
+            let peer_name = query_value(&query, "name").unwrap_or_else(|| peer_addr.to_string());
+            self.peer_names.lock().await.insert(peer_addr, peer_name);
+            Client::Peer
+        };
+
+        self.handle_connection(peer_addr, ws_stream, client).await;
+    }
+
+    pub async fn participants(&self) -> usize {
+        // Every connected peer plus the host itself
+        self.peer_names.lock().await.len() + 1
+    }
+
+    pub async fn participant_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.peer_names.lock().await.values().cloned().collect();
+        if let Some(name) = self.local_name.lock().await.clone() {
+            names.push(name);
+        }
+        names
+    }
+
+    async fn handle_connection(
+        &self,
+        addr: SocketAddr,
+        ws_stream: WebSocketStream<TcpStream>,
+        client: Client,
+    ) {
         let (mut outgoing, mut incoming) = ws_stream.split();
         let (pre_outgoing_sender, pre_outgoing_receiver) = flume::unbounded::<TimerMessage>();
 
         {
             let mut peer_map = self.peer_map.lock().await;
             peer_map.insert(addr, pre_outgoing_sender.clone());
+            METRICS.peer_connected();
             debug!("HOST(PEER_MAP): Inserted peer: {:?}", peer_map);
         }
 
         loop {
             tokio::select! {
                 Some(message) = incoming.next() => {
-                    if let Ok(message) = message {
-                        let timer_message: TimerMessage = serde_json::from_str(&message.to_string()).unwrap();
+                    let Ok(message) = message else {
+                        continue;
+                    };
+                    if let Ok(mut timer_message) = serde_json::from_str::<TimerMessage>(&message.to_string()) {
                         debug!("HOST(INCOMING): Message RECEIVED FROM client: {:?}", timer_message);
+                        if !client.may_send(timer_message.action) {
+                            debug!("HOST(INCOMING): Dropped message not permitted for {:?}", client);
+                            continue;
+                        }
+                        timer_message.sender = addr;
                         self.ws_to_app_sender.send_async(timer_message.clone()).await.unwrap();
                         debug!("HOST(WS_TO_APP): Message SENT TO app");
 
@@ -109,6 +192,8 @@ impl WebSocketHandler {
 
         let mut peer_map = self.peer_map.lock().await;
         peer_map.remove(&addr);
+        METRICS.peer_disconnected();
+        self.peer_names.lock().await.remove(&addr);
     }
 
     async fn broadcast(&self, timer_message: TimerMessage) {
@@ -127,7 +212,9 @@ impl WebSocketHandler {
                     "HOST(PRE_OUTGOING): Failed to stage message for client({:?})",
                     addr
                 );
+                continue;
             }
+            METRICS.message_relayed();
             debug!(
                 "HOST(PRE_OUTGOING): Stage message for CLIENT({:?}): {:?}",
                 addr, timer_message
@@ -135,43 +222,257 @@ impl WebSocketHandler {
         }
     }
 
-    pub async fn join(self, addr: &SocketAddr) {
-        let ws_addr = format!("ws://{}", addr).into_client_request().unwrap();
+    pub async fn join(self, addr: &SocketAddr, name: String) {
+        let ws_stream = self.connect(addr, name).await.expect("Failed to connect");
+        let local_addr = self.connected_from().await;
+        relay(
+            ws_stream,
+            local_addr,
+            self.ws_to_app_sender,
+            self.app_to_ws_receiver,
+        )
+        .await;
+    }
 
-        let (ws_stream, _) = tokio_tungstenite::connect_async(ws_addr)
-            .await
-            .expect("Failed to connect");
+    /// Opens the websocket to the host and records the local address it was opened from.
+    pub async fn connect(
+        &self,
+        addr: &SocketAddr,
+        name: String,
+    ) -> Result<ClientStream, tungstenite::Error> {
+        let ws_addr =
+            format!("ws://{}/?name={}", addr, encode_query_value(&name)).into_client_request()?;
+        *self.local_name.lock().await = Some(name);
 
-        let tcp_strem = match ws_stream.get_ref() {
+        let (ws_stream, _) = tokio_tungstenite::connect_async(ws_addr).await?;
+
+        let tcp_stream = match ws_stream.get_ref() {
             MaybeTlsStream::Plain(tcp_stream) => tcp_stream,
             _ => panic!("Expected Plain stream"),
         };
 
-        let local_addr = tcp_strem.local_addr().unwrap();
+        let local_addr = tcp_stream.local_addr()?;
         debug!("{:?} joined the session", local_addr);
         *self.local_addr.lock().await = Some(local_addr);
 
-        let (mut outgoing, mut incoming) = ws_stream.split();
+        Ok(ws_stream)
+    }
 
-        loop {
-            tokio::select! {
-                Some(Ok(message)) = incoming.next() => {
-                    let timer_message = serde_json::from_str(&message.to_string()).unwrap();
-                    debug!("{:?} - JOIN(INCOMING): Message RECEIVED FROM client: {:?}", local_addr, timer_message);
-                    self.ws_to_app_sender.send_async(timer_message).await.unwrap();
-                    debug!("{:?} - JOIN(WS_TO_APP): Message SENT TO app", local_addr);
+    /// The local address the websocket to the host was opened from.
+    pub async fn connected_from(&self) -> SocketAddr {
+        self.local_addr
+            .lock()
+            .await
+            .unwrap_or(SocketAddr::from(([0, 0, 0, 0], 0)))
+    }
+}
+
+/// Passes messages between the host and the app until either side goes away. It owns only the
+/// relay's ends of the channels, so the app's ends disconnect once the host hangs up and the
+/// relay stops once the app drops its ends.
+pub async fn relay(
+    ws_stream: ClientStream,
+    local_addr: SocketAddr,
+    ws_to_app_sender: flume::Sender<TimerMessage>,
+    app_to_ws_receiver: flume::Receiver<TimerMessage>,
+) {
+    let (mut outgoing, mut incoming) = ws_stream.split();
+
+    loop {
+        tokio::select! {
+            message = incoming.next() => {
+                let Some(Ok(message)) = message else {
+                    debug!("{:?} - JOIN(INCOMING): Connection to the host closed", local_addr);
+                    break;
+                };
+                let Ok(timer_message) = serde_json::from_str(&message.to_string()) else {
+                    debug!("{:?} - JOIN(INCOMING): Ignoring malformed message: {:?}", local_addr, message);
+                    continue;
+                };
+                debug!("{:?} - JOIN(INCOMING): Message RECEIVED FROM client: {:?}", local_addr, timer_message);
+                if ws_to_app_sender.send_async(timer_message).await.is_err() {
+                    break;
                 }
-                Ok(timer_message) = self.app_to_ws_receiver.recv_async() => {
-                    let message = serde_json::to_string(&timer_message).unwrap();
-                    debug!("{:?} - JOIN(APP_TO_WS): Message FROM app: {:?}", local_addr, timer_message);
-                    if outgoing.send(Message::text(message)).await.is_err() {
-                        debug!("{:?} - JOIN(OUTGOING): Failed to send message to WS", local_addr);
-                        break;
-                    }
-                    debug!("{:?} - JOIN(OUTGOING): Message SENT TO WS", local_addr);
+                debug!("{:?} - JOIN(WS_TO_APP): Message SENT TO app", local_addr);
+            }
+            timer_message = app_to_ws_receiver.recv_async() => {
+                let Ok(timer_message) = timer_message else {
+                    break;
+                };
+                let message = serde_json::to_string(&timer_message).unwrap();
+                debug!("{:?} - JOIN(APP_TO_WS): Message FROM app: {:?}", local_addr, timer_message);
+                if outgoing.send(Message::text(message)).await.is_err() {
+                    debug!("{:?} - JOIN(OUTGOING): Failed to send message to WS", local_addr);
+                    break;
                 }
-                else => break,
+                debug!("{:?} - JOIN(OUTGOING): Message SENT TO WS", local_addr);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Client {
+    Peer,
+    Browser { browser_control: bool },
+}
+
+impl Client {
+    fn may_send(&self, action: TimerAction) -> bool {
+        match self {
+            // Changing the timer for everyone goes through a vote, only the host settles one or
+            // ends the session
+            Client::Peer => !matches!(
+                action,
+                TimerAction::Sync(_)
+                    | TimerAction::Resolve(_)
+                    | TimerAction::Quit
+                    | TimerAction::Skip
+                    | TimerAction::Restart
+                    | TimerAction::Extend(_)
+                    | TimerAction::Next
+            ),
+            Client::Browser { browser_control } => {
+                *browser_control && matches!(action, TimerAction::Pause)
+            }
+        }
+    }
+}
+
+const INDEX_HTML: &str = include_str!("web/index.html");
+
+async fn is_websocket_upgrade(socket: &TcpStream) -> bool {
+    let mut buf = [0; 4096];
+
+    // Peek until the whole request head has arrived, leaving it in place for the handshake
+    for _ in 0..50 {
+        let Ok(n) = socket.peek(&mut buf).await else {
+            return false;
+        };
+        let head = String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+
+        if head.contains("\r\n\r\n") || n == buf.len() || n == 0 {
+            return head
+                .lines()
+                .any(|line| line.starts_with("upgrade:") && line.contains("websocket"));
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+
+    false
+}
+
+async fn serve_page(socket: &mut TcpStream, browser_control: bool) -> std::io::Result<()> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut buf = [0; 4096];
+    let n = socket.read(&mut buf).await?;
+    let request_line = String::from_utf8_lossy(&buf[..n]);
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+
+    let response = match path.split('?').next() {
+        Some("/") | Some("/index.html") => {
+            let body = INDEX_HTML.replace("{{CONTROLS}}", &browser_control.to_string());
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        }
+        Some("/metrics") => {
+            let body = METRICS.render();
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        }
+        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+    };
+
+    socket.write_all(response.as_bytes()).await?;
+    socket.shutdown().await
+}
+
+/// Percent-encodes everything but ASCII alphanumerics so any display name fits in the query.
+fn encode_query_value(name: &str) -> String {
+    name.bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn query_value(query: &str, key: &str) -> Option<String> {
+    let encoded = query.split('&').find_map(|pair| {
+        pair.split_once('=')
+            .filter(|(name, _)| *name == key)
+            .map(|(_, value)| value)
+    })?;
+
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut chars = encoded.bytes();
+    while let Some(byte) = chars.next() {
+        match byte {
+            b'%' => {
+                let hex = [chars.next()?, chars.next()?];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
             }
+            _ => bytes.push(byte),
         }
     }
+
+    String::from_utf8(bytes)
+        .ok()
+        .filter(|name| !name.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vote::Proposal;
+    use std::time::Duration;
+
+    #[test]
+    fn test_query_round_trip() {
+        for name in ["alice", "Bob Smith", "jöhn&co=1"] {
+            let query = format!("client=peer&name={}", encode_query_value(name));
+            assert_eq!(query_value(&query, "name").as_deref(), Some(name));
+        }
+
+        assert_eq!(query_value("other=1", "name"), None);
+        assert_eq!(query_value("name=", "name"), None);
+        assert_eq!(query_value("name=%4", "name"), None);
+        assert_eq!(
+            query_value("client=browser", "client").as_deref(),
+            Some("browser")
+        );
+    }
+
+    #[test]
+    fn test_browser_permissions() {
+        let watcher = Client::Browser {
+            browser_control: false,
+        };
+        let controller = Client::Browser {
+            browser_control: true,
+        };
+
+        assert!(!watcher.may_send(TimerAction::Pause));
+        assert!(controller.may_send(TimerAction::Pause));
+        assert!(!controller.may_send(TimerAction::Quit));
+        assert!(!Client::Peer.may_send(TimerAction::Quit));
+        assert!(!Client::Peer.may_send(TimerAction::Resolve(true)));
+    }
+
+    #[test]
+    fn test_peers_vote_on_changes() {
+        assert!(!Client::Peer.may_send(TimerAction::Skip));
+        assert!(!Client::Peer.may_send(TimerAction::Restart));
+        assert!(!Client::Peer.may_send(TimerAction::Extend(Duration::from_secs(60))));
+        assert!(!Client::Peer.may_send(TimerAction::Next));
+        assert!(Client::Peer.may_send(TimerAction::Propose(Proposal::Skip)));
+        assert!(Client::Peer.may_send(TimerAction::Vote(true)));
+    }
 }